@@ -0,0 +1,112 @@
+use crate::debug::{flush_debug_commands, in_debug_channel, DebugChannel};
+use crate::gizmos::Gizmos;
+use alloc::vec::Vec;
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_math::{ops, Vec2, Vec3Swizzles};
+use bevy_reflect::Reflect;
+use bevy_transform::prelude::{GlobalTransform, Transform};
+use bevy_transform::TransformSystem;
+use core::f32::consts::TAU;
+use playdate::graphics::color::LCDColorConst;
+use playdate::sys::ffi::LCDColor;
+
+/// Registers [`Collider`] and draws colliders while the [`DebugChannel::Physics`] channel is enabled.
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Collider>().add_systems(
+            PostUpdate,
+            draw_colliders
+                .run_if(in_debug_channel(DebugChannel::Physics))
+                .after(TransformSystem::TransformPropagate)
+                .before(flush_debug_commands),
+        );
+    }
+}
+
+/// A collision shape in the entity's local space.
+///
+/// Rects and ellipses extend right and down from the entity's origin, like Tiled objects,
+/// so a rotated rect is a [`Collider::Rect`] on a rotated [`Transform`].
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+#[require(Transform)]
+pub enum Collider {
+    Rect { size: Vec2 },
+    /// Ellipse inscribed in the rect of `size`.
+    Ellipse { size: Vec2 },
+    /// Closed polygon through `points`.
+    Polygon { points: Vec<Vec2> },
+}
+
+impl Collider {
+    /// Number of points used to approximate ellipses in [`Collider::outline`].
+    const ELLIPSE_SEGMENTS: usize = 16;
+
+    /// Corners of the shape in local space. Ellipses are approximated.
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self {
+            Collider::Rect { size } => alloc::vec![
+                Vec2::ZERO,
+                Vec2::new(size.x, 0.0),
+                *size,
+                Vec2::new(0.0, size.y),
+            ],
+            Collider::Ellipse { size } => {
+                let radius = *size / 2.0;
+                (0..Self::ELLIPSE_SEGMENTS)
+                    .map(|i| {
+                        let (sin, cos) = ops::sin_cos(i as f32 / Self::ELLIPSE_SEGMENTS as f32 * TAU);
+                        radius + Vec2::new(cos, sin) * radius
+                    })
+                    .collect()
+            }
+            Collider::Polygon { points } => points.clone(),
+        }
+    }
+
+    /// Whether `point`, in local space, is inside the shape.
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            Collider::Rect { size } => point.cmpge(Vec2::ZERO).all() && point.cmple(*size).all(),
+            Collider::Ellipse { size } => {
+                let radius = *size / 2.0;
+                ((point - radius) / radius).length_squared() <= 1.0
+            }
+            Collider::Polygon { points } => {
+                // even-odd rule
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    if (a.y > point.y) != (b.y > point.y)
+                        && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    /// World-space bounding box as `(min, max)`, e.g. for a sprite's collide rect.
+    pub fn aabb(&self, transform: &GlobalTransform) -> (Vec2, Vec2) {
+        self.outline()
+            .into_iter()
+            .map(|p| transform.transform_point(p.extend(0.0)).xy())
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), p| (min.min(p), max.max(p)))
+    }
+}
+
+pub fn draw_colliders(mut gizmos: Gizmos, q_colliders: Query<(&Collider, &GlobalTransform)>) {
+    for (collider, transform) in &q_colliders {
+        let outline = collider
+            .outline()
+            .into_iter()
+            .map(|p| transform.transform_point(p.extend(0.0)).xy());
+        gizmos.world().polyline(outline, true, LCDColor::XOR);
+    }
+}
@@ -1,11 +1,19 @@
+pub mod log;
+pub mod profiler;
+
 use alloc::collections::VecDeque;
-use bevy_app::{App, Plugin, PostUpdate};
+use alloc::string::String;
+use alloc::vec::Vec;
+use bevy_app::{App, First, Plugin, PostUpdate, Startup};
 use bevy_ecs::observer::Trigger;
 use bevy_ecs::prelude::{IntoSystemConfigs, Resource};
-use bevy_ecs::system::{Res, ResMut};
+use bevy_ecs::system::{NonSendMut, Res, ResMut};
+use bevy_ecs::world::World;
+use core::ffi::{c_int, CStr};
 use playdate::api;
+use playdate::graphics::text::draw_text;
 use playdate::sprite::draw_sprites;
-use playdate::sys::ffi::LCDColor;
+use playdate::sys::ffi::{LCDColor, PDMenuItem};
 use playdate::system::System;
 use crate::event::SystemEvent;
 
@@ -38,9 +46,21 @@ pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(profiler::ProfilerPlugin);
         app.init_resource::<Debug>()
+            .init_resource::<DebugBindings>()
             .add_observer(toggle_debug_system)
-            .add_systems(PostUpdate, draw_fps_top_left.after(draw_sprites).run_if(in_debug));
+            .add_systems(Startup, setup_debug_menu)
+            .add_systems(First, sync_debug_menu)
+            .add_systems(
+                PostUpdate,
+                (
+                    draw_fps_top_left.run_if(in_debug_channel(DebugChannel::Fps)),
+                    flush_debug_commands,
+                )
+                    .chain()
+                    .after(draw_sprites),
+            );
     }
 }
 
@@ -48,32 +68,240 @@ pub fn in_debug(debug: Res<Debug>) -> bool {
     debug.enabled
 }
 
+/// Run condition that is true while debug mode and `channel` are both enabled.
+pub fn in_debug_channel(channel: DebugChannel) -> impl FnMut(Res<Debug>) -> bool + Clone {
+    move |debug: Res<Debug>| debug.is_enabled(channel)
+}
+
+/// Draws `text` with its top left corner at `(x, y)`.
+/// Errors are ignored, as a failed debug label isn't worth panicking over.
+pub fn debug_text(text: &str, x: i32, y: i32) {
+    let _ = draw_text(text, x, y);
+}
+
 pub fn draw_fps_top_left() {
     System::Default().draw_fps(0, 0);
 }
 
+/// Toggles debug mode and its channels from simulator key presses, see [`DebugBindings`].
 pub fn toggle_debug_system(
     trigger: Trigger<SystemEvent>,
     mut debug: ResMut<Debug>,
+    bindings: Res<DebugBindings>,
 ) {
-    const BACKTICK: u32 = 96;
-    if matches!(*trigger.event(), SystemEvent::KeyPressed(96)) {
+    let SystemEvent::KeyPressed(key) = *trigger.event() else {
+        return;
+    };
+
+    if key == bindings.toggle {
         debug.toggle_enabled();
     }
+    for &(_, channel) in bindings.channels.iter().filter(|(k, _)| *k == key) {
+        debug.toggle_channel(channel);
+    }
+}
+
+/// Draws every queued [`Debug`] command if debug mode is enabled, otherwise discards them.
+///
+/// Runs after [`draw_sprites`] so the commands are drawn on top of the sprites.
+pub fn flush_debug_commands(mut debug: ResMut<Debug>) {
+    if debug.enabled {
+        debug.draw();
+    } else {
+        debug.clear();
+    }
+}
+
+/// A category of debug output that can be shown or hidden independently.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DebugChannel {
+    /// FPS counter in the top left corner.
+    Fps,
+    /// Bounds and positions of sprites.
+    SpriteBounds,
+    /// Shapes drawn through [`crate::gizmos::Gizmos`].
+    Gizmos,
+    /// Simulation state, such as velocities.
+    Physics,
+    /// The [`profiler::Profiler`] overlay.
+    Profiler,
+}
+
+impl DebugChannel {
+    pub const ALL: [DebugChannel; 5] = [
+        DebugChannel::Fps,
+        DebugChannel::SpriteBounds,
+        DebugChannel::Gizmos,
+        DebugChannel::Physics,
+        DebugChannel::Profiler,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            DebugChannel::Fps => "fps",
+            DebugChannel::SpriteBounds => "bounds",
+            DebugChannel::Gizmos => "gizmos",
+            DebugChannel::Physics => "physics",
+            DebugChannel::Profiler => "profiler",
+        }
+    }
+
+    const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Simulator key codes that toggle debug mode and its channels.
+#[derive(Resource, Clone, Debug)]
+pub struct DebugBindings {
+    /// Toggles debug mode. Defaults to backtick.
+    pub toggle: u32,
+    /// Toggles a single channel. Defaults to the number keys `1`-`5`.
+    pub channels: Vec<(u32, DebugChannel)>,
+}
+
+impl Default for DebugBindings {
+    fn default() -> Self {
+        const BACKTICK: u32 = 96;
+        const ONE: u32 = 49;
+
+        Self {
+            toggle: BACKTICK,
+            channels: DebugChannel::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, &channel)| (ONE + i as u32, channel))
+                .collect(),
+        }
+    }
 }
 
-// pub fn debug_print
+/// Options of the "debug" system menu entry.
+/// Choosing a channel shows only that channel, "all" shows every channel.
+const MENU_OPTIONS: [&CStr; 7] = [
+    c"off", c"all", c"fps", c"bounds", c"gizmos", c"physics", c"profiler",
+];
 
-#[derive(Resource, Default)]
+/// Handle to the "debug" entry in the Playdate system menu.
+pub struct DebugMenu {
+    item: *mut PDMenuItem,
+    /// Last value seen, so changes made in code aren't overwritten by the menu.
+    value: c_int,
+}
+
+impl Drop for DebugMenu {
+    fn drop(&mut self) {
+        unsafe { api!(system).removeMenuItem.unwrap()(self.item) };
+    }
+}
+
+/// Adds the "debug" options entry to the system menu.
+pub fn setup_debug_menu(world: &mut World) {
+    let mut titles = MENU_OPTIONS.map(CStr::as_ptr);
+    let item = unsafe {
+        api!(system).addOptionsMenuItem.unwrap()(
+            c"debug".as_ptr(),
+            titles.as_mut_ptr(),
+            titles.len() as c_int,
+            None,
+            core::ptr::null_mut(),
+        )
+    };
+
+    if item.is_null() {
+        // the system menu only has room for three custom entries
+        crate::warn!("could not add debug menu item");
+        return;
+    }
+
+    let value = menu_value(world.resource::<Debug>());
+    unsafe { api!(system).setMenuItemValue.unwrap()(item, value) };
+    world.insert_non_send_resource(DebugMenu { item, value });
+}
+
+/// Applies changes to the "debug" system menu entry to [`Debug`].
+pub fn sync_debug_menu(mut debug: ResMut<Debug>, menu: Option<NonSendMut<DebugMenu>>) {
+    let Some(mut menu) = menu else {
+        return;
+    };
+
+    let value = unsafe { api!(system).getMenuItemValue.unwrap()(menu.item) };
+    if value == menu.value {
+        return;
+    }
+    menu.value = value;
+
+    match value {
+        0 => debug.enabled = false,
+        1 => {
+            debug.enabled = true;
+            debug.channels = u8::MAX;
+        }
+        _ => {
+            debug.enabled = true;
+            debug.channels = DebugChannel::ALL[value as usize - 2].mask();
+        }
+    }
+}
+
+fn menu_value(debug: &Debug) -> c_int {
+    if !debug.enabled {
+        return 0;
+    }
+    DebugChannel::ALL
+        .iter()
+        .position(|c| c.mask() == debug.channels)
+        .map_or(1, |i| i as c_int + 2)
+}
+
+#[derive(Resource)]
 pub struct Debug {
     pub enabled: bool,
+    channels: u8,
     command_queue: VecDeque<DebugCommand>,
 }
 
+impl Default for Debug {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            channels: u8::MAX,
+            command_queue: VecDeque::new(),
+        }
+    }
+}
+
 impl Debug {
     pub fn toggle_enabled(&mut self) {
         self.enabled = !self.enabled;
     }
+
+    /// Whether debug mode and `channel` are both enabled.
+    pub fn is_enabled(&self, channel: DebugChannel) -> bool {
+        self.enabled && self.channel_enabled(channel)
+    }
+
+    /// Whether `channel` is shown when debug mode is enabled.
+    pub fn channel_enabled(&self, channel: DebugChannel) -> bool {
+        self.channels & channel.mask() != 0
+    }
+
+    pub fn set_channel(&mut self, channel: DebugChannel, enabled: bool) {
+        if enabled {
+            self.channels |= channel.mask();
+        } else {
+            self.channels &= !channel.mask();
+        }
+    }
+
+    pub fn toggle_channel(&mut self, channel: DebugChannel) {
+        self.channels ^= channel.mask();
+    }
+
+    /// Discards all queued commands without drawing them.
+    pub fn clear(&mut self) {
+        self.command_queue.clear();
+    }
 }
 
 enum DebugCommand {
@@ -90,6 +318,22 @@ enum DebugCommand {
         color: LCDColor,
         filled: bool,
     },
+    Rect {
+        origin: (i32, i32),
+        size: (i32, i32),
+        color: LCDColor,
+        filled: bool,
+    },
+    Polyline {
+        points: Vec<(i32, i32)>,
+        closed: bool,
+        line_width: i32,
+        color: LCDColor,
+    },
+    Text {
+        position: (i32, i32),
+        text: String,
+    },
 }
 
 impl Debug {
@@ -119,6 +363,39 @@ impl Debug {
         });
     }
 
+    pub fn rect(&mut self, origin: (i32, i32), size: (i32, i32), color: LCDColor, filled: bool) {
+        self.command_queue.push_back(DebugCommand::Rect {
+            origin,
+            size,
+            color,
+            filled,
+        });
+    }
+
+    /// Queues connected line segments between each consecutive point.
+    /// If `closed`, the last point is also connected back to the first.
+    pub fn polyline(
+        &mut self,
+        points: Vec<(i32, i32)>,
+        closed: bool,
+        line_width: i32,
+        color: LCDColor,
+    ) {
+        self.command_queue.push_back(DebugCommand::Polyline {
+            points,
+            closed,
+            line_width,
+            color,
+        });
+    }
+
+    pub fn text(&mut self, position: (i32, i32), text: impl Into<String>) {
+        self.command_queue.push_back(DebugCommand::Text {
+            position,
+            text: text.into(),
+        });
+    }
+
     pub fn draw(&mut self) {
         for command in self.command_queue.drain(..) {
             match command {
@@ -142,8 +419,8 @@ impl Debug {
                     if filled {
                         unsafe {
                             api!(graphics).fillEllipse.unwrap()(
-                                center.0,
-                                center.1,
+                                center.0 - radius,
+                                center.1 - radius,
                                 radius * 2,
                                 radius * 2,
                                 0.0,
@@ -154,8 +431,8 @@ impl Debug {
                     } else {
                         unsafe {
                             api!(graphics).drawEllipse.unwrap()(
-                                center.0,
-                                center.1,
+                                center.0 - radius,
+                                center.1 - radius,
                                 radius * 2,
                                 radius * 2,
                                 line_width,
@@ -166,6 +443,39 @@ impl Debug {
                         }
                     }
                 }
+                DebugCommand::Rect {
+                    origin,
+                    size,
+                    color,
+                    filled,
+                } => unsafe {
+                    if filled {
+                        api!(graphics).fillRect.unwrap()(origin.0, origin.1, size.0, size.1, color);
+                    } else {
+                        api!(graphics).drawRect.unwrap()(origin.0, origin.1, size.0, size.1, color);
+                    }
+                },
+                DebugCommand::Polyline {
+                    points,
+                    closed,
+                    line_width,
+                    color,
+                } => {
+                    let closing = if closed && points.len() > 2 {
+                        points.first().map(|first| (*points.last().unwrap(), *first))
+                    } else {
+                        None
+                    };
+
+                    for (start, end) in points.windows(2).map(|w| (w[0], w[1])).chain(closing) {
+                        unsafe {
+                            api!(graphics).drawLine.unwrap()(
+                                start.0, start.1, end.0, end.1, line_width, color,
+                            );
+                        }
+                    }
+                }
+                DebugCommand::Text { position, text } => debug_text(&text, position.0, position.1),
             }
         }
     }
@@ -0,0 +1,135 @@
+//! Leveled logging to the Playdate console, filterable by module at runtime.
+//!
+//! ```ignore
+//! use bevy_playdate::{info, warn};
+//! use bevy_playdate::debug::log::{set_module_level, LogLevel};
+//!
+//! set_module_level("my_game::physics", Some(LogLevel::Trace));
+//! info!("loaded {} segments", segments.len());
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt;
+
+/// Prints to the console if `level` is enabled for the calling module.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::debug::log::enabled($level, module_path!()) {
+            playdate::println!("[{} {}] {}", $level, module_path!(), format_args!($($arg)+))
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::debug::log::LogLevel::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::debug::log::LogLevel::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::debug::log::LogLevel::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::debug::log::LogLevel::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::debug::log::LogLevel::Trace, $($arg)+) };
+}
+
+/// Severity of a log message, from most to least severe.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        })
+    }
+}
+
+/// Most verbose level shown per module. `None` turns logging off.
+struct LogFilter {
+    default: Option<LogLevel>,
+    modules: Vec<(String, Option<LogLevel>)>,
+}
+
+struct GlobalFilter(UnsafeCell<LogFilter>);
+
+// SAFETY: The Playdate is single-threaded,
+// and no reference into the filter outlives the functions below.
+unsafe impl Sync for GlobalFilter {}
+
+static FILTER: GlobalFilter = GlobalFilter(UnsafeCell::new(LogFilter {
+    default: Some(LogLevel::Info),
+    modules: Vec::new(),
+}));
+
+fn with_filter<T>(f: impl FnOnce(&mut LogFilter) -> T) -> T {
+    // SAFETY: see `GlobalFilter`
+    f(unsafe { &mut *FILTER.0.get() })
+}
+
+/// Sets the most verbose level shown for modules without their own level.
+/// Defaults to [`LogLevel::Info`].
+pub fn set_level(level: Option<LogLevel>) {
+    with_filter(|filter| filter.default = level);
+}
+
+/// Sets the most verbose level shown for `module` and its submodules,
+/// e.g. `"bevy_playdate::jobs"`. Overrides less specific module levels.
+pub fn set_module_level(module: &str, level: Option<LogLevel>) {
+    with_filter(|filter| {
+        match filter.modules.iter_mut().find(|(m, _)| m == module) {
+            Some((_, l)) => *l = level,
+            None => filter.modules.push((module.to_string(), level)),
+        }
+    });
+}
+
+/// Removes all per-module levels set with [`set_module_level`].
+pub fn clear_module_levels() {
+    with_filter(|filter| filter.modules.clear());
+}
+
+/// Whether a message at `level` from `module` should be printed.
+pub fn enabled(level: LogLevel, module: &str) -> bool {
+    with_filter(|filter| {
+        let max = filter
+            .modules
+            .iter()
+            .filter(|(prefix, _)| is_module_or_child(module, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(filter.default, |(_, level)| *level);
+
+        max.is_some_and(|max| level <= max)
+    })
+}
+
+fn is_module_or_child(module: &str, prefix: &str) -> bool {
+    module
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
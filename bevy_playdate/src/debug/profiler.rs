@@ -0,0 +1,288 @@
+use crate::debug::{debug_text, in_debug, in_debug_channel, Debug, DebugChannel};
+use crate::time::Time;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::vec::Vec;
+use bevy_app::{App, MainScheduleOrder, Plugin, PostUpdate};
+use bevy_ecs::intern::Interned;
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::{ScheduleLabel, SystemConfigs};
+use bevy_ecs::system::SystemId;
+use core::time::Duration;
+use playdate::api;
+use playdate::graphics::color::LCDColorConst;
+use playdate::println;
+use playdate::sprite::draw_sprites;
+use playdate::sys::ffi::LCDColor;
+
+/// Measures the time spent in each schedule of the main loop, and in any systems
+/// wrapped with [`profiled`].
+///
+/// Only measures while debug mode is enabled.
+/// See [`Profiler`] for how the results are shown.
+pub struct ProfilerPlugin;
+
+impl Plugin for ProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Profiler>()
+            .add_systems(ProfiledMain, run_main_profiled)
+            .add_systems(
+                PostUpdate,
+                (
+                    draw_profiler.run_if(in_debug_channel(DebugChannel::Profiler)),
+                    dump_profiler_csv.run_if(in_debug),
+                )
+                    .after(draw_sprites)
+                    .after(super::flush_debug_commands),
+            );
+
+        app.main_mut().update_schedule = Some(ProfiledMain.intern());
+    }
+}
+
+/// Replaces [`bevy_app::Main`] as the schedule run by `App::update`
+/// when the [`ProfilerPlugin`] is added, so each schedule can be timed.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProfiledMain;
+
+/// Runs the schedules in [`MainScheduleOrder`] like [`bevy_app::Main::run_main`],
+/// timing each one.
+pub fn run_main_profiled(world: &mut World, mut run_at_least_once: Local<bool>) {
+    if !*run_at_least_once {
+        world.resource_scope(|world, order: Mut<MainScheduleOrder>| {
+            for &label in &order.startup_labels {
+                let _ = world.try_run_schedule(label);
+            }
+        });
+        *run_at_least_once = true;
+    }
+
+    world.resource_scope(|world, order: Mut<MainScheduleOrder>| {
+        for &label in &order.labels {
+            let start = profile_start(world);
+            let _ = world.try_run_schedule(label);
+            profile_end(world, start, || Section::Schedule(label));
+        }
+    });
+
+    if world.resource::<Debug>().enabled {
+        world.resource_mut::<Profiler>().end_frame();
+    }
+}
+
+/// Wraps a system so its run time is recorded by the [`Profiler`].
+///
+/// The wrapped system runs as a one-shot system inside an exclusive system. That has a cost:
+/// it no longer runs in parallel with other systems, and other systems can't be ordered
+/// relative to the original function, only to the returned configs (e.g. in a set).
+///
+/// ```ignore
+/// app.add_systems(Update, profiled(move_spline_dot));
+/// ```
+pub fn profiled<M>(system: impl IntoSystem<(), (), M> + 'static) -> SystemConfigs {
+    let system = IntoSystem::into_system(system);
+    let name = system.name();
+    let mut system = Some(system);
+    let mut id: Option<SystemId> = None;
+
+    (move |world: &mut World| {
+        let id = *id.get_or_insert_with(|| world.register_system(system.take().unwrap()));
+        let start = profile_start(world);
+        // errors are already reported by the one-shot system runner
+        let _ = world.run_system(id);
+        profile_end(world, start, || Section::System(name.clone()));
+    })
+    .into_configs()
+}
+
+fn profile_start(world: &World) -> Option<Duration> {
+    world
+        .resource::<Debug>()
+        .enabled
+        .then(|| world.resource::<Time>().now())
+}
+
+fn profile_end(world: &mut World, start: Option<Duration>, section: impl FnOnce() -> Section) {
+    let Some(start) = start else {
+        return;
+    };
+    let elapsed = world.resource::<Time>().now().saturating_sub(start);
+    world.resource_mut::<Profiler>().record(section(), elapsed);
+}
+
+/// A measured section of the frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Section {
+    Schedule(Interned<dyn ScheduleLabel>),
+    System(Cow<'static, str>),
+}
+
+impl Section {
+    fn label(&self) -> Cow<'static, str> {
+        match self {
+            Section::Schedule(label) => Cow::Owned(format!("{label:?}")),
+            Section::System(name) => {
+                // drop the module path, the screen is not wide enough
+                match name.rsplit_once("::") {
+                    Some((_, short)) => Cow::Owned(short.into()),
+                    None => name.clone(),
+                }
+            }
+        }
+    }
+}
+
+/// Aggregated timings of a [`Section`] over the last [`Profiler::window`] frames.
+#[derive(Clone, Debug)]
+pub struct SectionStats {
+    pub section: Section,
+    samples: Vec<Duration>,
+    /// Time spent in this section in the frame that is still running.
+    current: Duration,
+}
+
+impl SectionStats {
+    pub fn min(&self) -> Duration {
+        self.samples.iter().copied().min().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().copied().max().unwrap_or_default()
+    }
+
+    pub fn avg(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+}
+
+/// Per-schedule and per-system frame timings.
+///
+/// While debug mode is enabled, the timings are drawn as a bar chart if [`Profiler::overlay`] is set,
+/// and printed as CSV to the console every [`Profiler::window`] frames if [`Profiler::csv`] is set.
+#[derive(Resource)]
+pub struct Profiler {
+    /// Number of frames to aggregate min/avg/max over.
+    pub window: usize,
+    /// Frame time that a full-width bar represents.
+    pub budget: Duration,
+    /// Draws the timings as a bar chart.
+    pub overlay: bool,
+    /// Prints the timings as CSV once per window.
+    pub csv: bool,
+    sections: Vec<SectionStats>,
+    frame: usize,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            // the default refresh rate is 30 fps
+            budget: Duration::from_micros(33_333),
+            overlay: true,
+            csv: false,
+            sections: Vec::new(),
+            frame: 0,
+        }
+    }
+}
+
+impl Profiler {
+    /// Adds `elapsed` to the time spent in `section` this frame.
+    pub fn record(&mut self, section: Section, elapsed: Duration) {
+        match self.sections.iter_mut().find(|s| s.section == section) {
+            Some(stats) => stats.current += elapsed,
+            None => self.sections.push(SectionStats {
+                section,
+                samples: Vec::with_capacity(self.window),
+                current: elapsed,
+            }),
+        }
+    }
+
+    /// Moves this frame's timings into each section's window.
+    fn end_frame(&mut self) {
+        let slot = self.frame % self.window.max(1);
+        for stats in self.sections.iter_mut() {
+            let sample = core::mem::take(&mut stats.current);
+            if stats.samples.len() < self.window {
+                stats.samples.push(sample);
+            } else {
+                stats.samples[slot] = sample;
+            }
+        }
+        self.frame += 1;
+    }
+
+    pub fn sections(&self) -> &[SectionStats] {
+        &self.sections
+    }
+
+    /// Discards all recorded timings.
+    pub fn reset(&mut self) {
+        self.sections.clear();
+        self.frame = 0;
+    }
+
+    /// Prints `section,min_ms,avg_ms,max_ms` lines to the console.
+    pub fn dump_csv(&self) {
+        println!("section,min_ms,avg_ms,max_ms");
+        for stats in &self.sections {
+            println!(
+                "{},{:.3},{:.3},{:.3}",
+                stats.section.label(),
+                ms(stats.min()),
+                ms(stats.avg()),
+                ms(stats.max()),
+            );
+        }
+    }
+}
+
+fn ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+pub fn dump_profiler_csv(profiler: Res<Profiler>) {
+    if profiler.csv && profiler.frame > 0 && profiler.frame % profiler.window.max(1) == 0 {
+        profiler.dump_csv();
+    }
+}
+
+/// Draws the average time of each section as a horizontal bar on the right of the screen,
+/// with a tick at the maximum.
+pub fn draw_profiler(profiler: Res<Profiler>) {
+    const X: i32 = 200;
+    const Y: i32 = 24;
+    const WIDTH: i32 = 196;
+    const ROW_HEIGHT: i32 = 16;
+    const LABEL_WIDTH: i32 = 100;
+    const BAR_WIDTH: i32 = WIDTH - LABEL_WIDTH - 4;
+
+    if !profiler.overlay || profiler.sections.is_empty() {
+        return;
+    }
+
+    let budget = profiler.budget.as_secs_f32().max(f32::EPSILON);
+    let bar = |d: Duration| ((d.as_secs_f32() / budget) * BAR_WIDTH as f32).clamp(0.0, BAR_WIDTH as f32) as i32;
+    let height = profiler.sections.len() as i32 * ROW_HEIGHT + 4;
+
+    unsafe {
+        api!(graphics).fillRect.unwrap()(X, Y, WIDTH, height, LCDColor::WHITE);
+        api!(graphics).drawRect.unwrap()(X, Y, WIDTH, height, LCDColor::BLACK);
+    }
+
+    for (i, stats) in profiler.sections.iter().enumerate() {
+        let y = Y + 2 + i as i32 * ROW_HEIGHT;
+        let bar_x = X + LABEL_WIDTH;
+        debug_text(&format!("{:.1} {}", ms(stats.avg()), stats.section.label()), X + 2, y);
+        unsafe {
+            api!(graphics).fillRect.unwrap()(bar_x, y + 4, bar(stats.avg()), ROW_HEIGHT - 8, LCDColor::BLACK);
+            let max = bar_x + bar(stats.max());
+            api!(graphics).drawLine.unwrap()(max, y + 2, max, y + ROW_HEIGHT - 2, 1, LCDColor::BLACK);
+        }
+    }
+}
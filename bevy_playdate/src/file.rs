@@ -1,6 +1,9 @@
 ﻿use core::ffi::c_void;
 use core::ffi::c_int;
 use alloc::ffi::CString;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 
 use no_std_io2::io::{self, *};
 use playdate::sys;
@@ -8,39 +11,161 @@ use playdate::{fs::{api::{Api, Default}, File}, sys::traits::AsRaw};
 use derive_more::{Deref, DerefMut};
 use playdate::sys::ffi::SDFile;
 use playdate::sys::ffi::FileOptions;
+use crate::fs::{self as pd_fs, last_error, Location};
 
+/// An open file, with a read buffer so small reads don't each call into the SDK.
 pub struct FileHandle {
     handle: *mut SDFile,
+    path: String,
+    buf: Vec<u8>,
+    /// Position of the next unread byte in `buf`.
+    pos: usize,
+    /// Number of valid bytes in `buf`.
+    filled: usize,
 }
 
 impl FileHandle {
+    /// Size of the read buffer used by [`FileHandle::open`].
+    pub const DEFAULT_BUF_SIZE: usize = 1024;
+
     pub fn open(path: &str, mode: FileOptions) -> io::Result<Self> {
+        Self::with_capacity(path, mode, Self::DEFAULT_BUF_SIZE)
+    }
+
+    /// Opens a file with a read buffer of `capacity` bytes, at least 1.
+    /// Reads at least as large as the buffer bypass it.
+    pub fn with_capacity(path: &str, mode: FileOptions, capacity: usize) -> io::Result<Self> {
         let c_path = CString::new(path).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))?;
         let handle = unsafe { sys::api!(file).open.unwrap()(c_path.as_ptr(), mode) };
         if handle.is_null() {
-            Err(io::Error::new(io::ErrorKind::NotFound, "Failed to open file"))
+            Err(last_error(io::ErrorKind::NotFound))
         } else {
-            Ok(FileHandle { handle })
+            Ok(FileHandle {
+                handle,
+                path: path.into(),
+                buf: vec![0; capacity.max(1)],
+                pos: 0,
+                filled: 0,
+            })
         }
     }
-}
 
-impl Read for FileHandle {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn read_raw(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let result = unsafe { sys::api!(file).read.unwrap()(self.handle, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) };
         if result < 0 {
-            Err(io::Error::new(io::ErrorKind::Other, "Read error"))
+            Err(last_error(io::ErrorKind::Other))
         } else {
             Ok(result as usize)
         }
     }
+
+    fn seek_raw(&mut self, offset: c_int, whence: c_int) -> io::Result<u64> {
+        let result = unsafe { sys::api!(file).seek.unwrap()(self.handle, offset, whence) };
+        if result < 0 {
+            return Err(last_error(io::ErrorKind::Other));
+        }
+        // seek returns 0 on success, not the new position
+        let result = unsafe { sys::api!(file).tell.unwrap()(self.handle) };
+        if result < 0 {
+            Err(last_error(io::ErrorKind::Other))
+        } else {
+            Ok(result as u64)
+        }
+    }
+
+    /// Moves the file position back to the first unread byte and empties the read buffer,
+    /// so writes and seeks happen where the caller expects.
+    fn discard_buffer(&mut self) -> io::Result<()> {
+        let unread = self.filled - self.pos;
+        if unread > 0 {
+            self.seek_raw(-(unread as c_int), 1)?;
+        }
+        self.pos = 0;
+        self.filled = 0;
+        Ok(())
+    }
+}
+
+impl Read for FileHandle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // large reads skip the buffer instead of copying through it
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.read_raw(buf);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+
+    /// Reserves the size of the file up front, so the buffer is only allocated once.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        if let Ok(metadata) = pd_fs::metadata(&self.path) {
+            buf.reserve(metadata.size as usize);
+        }
+        let start = buf.len();
+        buf.extend_from_slice(&self.buf[self.pos..self.filled]);
+        self.pos = 0;
+        self.filled = 0;
+
+        loop {
+            if buf.len() == buf.capacity() {
+                buf.reserve(Self::DEFAULT_BUF_SIZE);
+            }
+            let len = buf.len();
+            buf.resize(buf.capacity(), 0);
+            match self.read_raw(&mut buf[len..]) {
+                Ok(0) => {
+                    buf.truncate(len);
+                    return Ok(len - start);
+                }
+                Ok(n) => buf.truncate(len + n),
+                Err(e) => {
+                    buf.truncate(len);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut bytes = Vec::new();
+        let n = self.read_to_end(&mut bytes)?;
+        let s = core::str::from_utf8(&bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8"))?;
+        buf.push_str(s);
+        Ok(n)
+    }
+}
+
+impl BufRead for FileHandle {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            let mut buf = core::mem::take(&mut self.buf);
+            let result = self.read_raw(&mut buf);
+            self.buf = buf;
+            self.filled = result?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
 }
 
 impl Write for FileHandle {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.discard_buffer()?;
         let result = unsafe { sys::api!(file).write.unwrap()(self.handle, buf.as_ptr() as *const c_void, buf.len() as u32) };
         if result < 0 {
-            Err(io::Error::new(io::ErrorKind::Other, "Write error"))
+            Err(last_error(io::ErrorKind::Other))
         } else {
             Ok(result as usize)
         }
@@ -49,7 +174,7 @@ impl Write for FileHandle {
     fn flush(&mut self) -> io::Result<()> {
         let result = unsafe { sys::api!(file).flush.unwrap()(self.handle) };
         if result < 0 {
-            Err(io::Error::new(io::ErrorKind::Other, "Flush error"))
+            Err(last_error(io::ErrorKind::Other))
         } else {
             Ok(())
         }
@@ -61,14 +186,12 @@ impl Seek for FileHandle {
         let (offset, whence) = match pos {
             SeekFrom::Start(n) => (n as c_int, 0),
             SeekFrom::End(n) => (n as c_int, 2),
-            SeekFrom::Current(n) => (n as c_int, 1),
+            // relative to what the caller has read, not to what was buffered
+            SeekFrom::Current(n) => (n as c_int - (self.filled - self.pos) as c_int, 1),
         };
-        let result = unsafe { sys::api!(file).seek.unwrap()(self.handle, offset, whence) };
-        if result < 0 {
-            Err(io::Error::new(io::ErrorKind::Other, "Seek error"))
-        } else {
-            Ok(result as u64)
-        }
+        self.pos = 0;
+        self.filled = 0;
+        self.seek_raw(offset, whence)
     }
 }
 
@@ -77,3 +200,66 @@ impl Drop for FileHandle {
         unsafe { sys::api!(file).close.unwrap()(self.handle) };
     }
 }
+
+/// A read-only file that is loaded into memory when opened if it is small enough,
+/// and read from the SDK in buffered chunks otherwise.
+pub enum AssetFile {
+    Loaded(Cursor<Vec<u8>>),
+    Streamed(FileHandle),
+}
+
+impl AssetFile {
+    /// Files up to this size are loaded by [`AssetFile::open`].
+    pub const DEFAULT_MAX_LOADED_SIZE: u32 = 16 * 1024;
+
+    pub fn open(path: &str, location: Location) -> io::Result<Self> {
+        Self::open_with_max(path, location, Self::DEFAULT_MAX_LOADED_SIZE)
+    }
+
+    /// Opens a file, loading it whole if it is at most `max_loaded_size` bytes.
+    pub fn open_with_max(path: &str, location: Location, max_loaded_size: u32) -> io::Result<Self> {
+        let mut file = FileHandle::open(path, location.read_options())?;
+        match pd_fs::metadata(path) {
+            Ok(metadata) if metadata.size <= max_loaded_size => {
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf)?;
+                Ok(AssetFile::Loaded(Cursor::new(buf)))
+            }
+            _ => Ok(AssetFile::Streamed(file)),
+        }
+    }
+}
+
+impl Read for AssetFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            AssetFile::Loaded(cursor) => cursor.read(buf),
+            AssetFile::Streamed(file) => file.read(buf),
+        }
+    }
+}
+
+impl BufRead for AssetFile {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            AssetFile::Loaded(cursor) => cursor.fill_buf(),
+            AssetFile::Streamed(file) => file.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            AssetFile::Loaded(cursor) => cursor.consume(amt),
+            AssetFile::Streamed(file) => file.consume(amt),
+        }
+    }
+}
+
+impl Seek for AssetFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            AssetFile::Loaded(cursor) => cursor.seek(pos),
+            AssetFile::Streamed(file) => file.seek(pos),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! The rest of the Playdate file API: listing, metadata, directories, renaming and deleting.
+//!
+//! Paths are relative to the game's data folder when writing, and to both the data folder
+//! and the pdx bundle when reading, with the data folder taking priority.
+//! Use [`Location`] to read from only one of them.
+//!
+//! Errors carry the message of the SDK's `geterr`.
+
+use crate::file::FileHandle;
+use alloc::ffi::CString;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_void, CStr};
+use no_std_io2::io::{self, Read};
+use playdate::sys;
+use playdate::sys::ffi::{FileOptions, FileStat};
+
+/// Where to read a file from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// The read-only pdx bundle the game shipped with.
+    Bundle,
+    /// The game's writable data folder.
+    Data,
+    /// The data folder, falling back to the bundle.
+    #[default]
+    Any,
+}
+
+impl Location {
+    pub fn read_options(self) -> FileOptions {
+        match self {
+            Location::Bundle => FileOptions::kFileRead,
+            Location::Data => FileOptions::kFileReadData,
+            Location::Any => FileOptions::kFileRead | FileOptions::kFileReadData,
+        }
+    }
+}
+
+/// Builds an [`io::Error`] from the message of the last failed file API call.
+pub(crate) fn last_error(kind: io::ErrorKind) -> io::Error {
+    let err = unsafe { sys::api!(file).geterr.unwrap()() };
+    if err.is_null() {
+        return io::Error::new(kind, "unknown file error");
+    }
+    let msg = unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned();
+    io::Error::new(kind, msg)
+}
+
+fn c_path(path: &str) -> io::Result<CString> {
+    CString::new(path).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))
+}
+
+/// Turns an SDK return code into a result.
+fn check(result: i32, kind: io::ErrorKind) -> io::Result<()> {
+    if result < 0 {
+        Err(last_error(kind))
+    } else {
+        Ok(())
+    }
+}
+
+/// Opens `path` for reading from `location`.
+pub fn open(path: &str, location: Location) -> io::Result<FileHandle> {
+    FileHandle::open(path, location.read_options())
+}
+
+/// Reads the whole file at `path` from `location`.
+pub fn read(path: &str, location: Location) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    open(path, location)?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Creates or truncates `path` in the data folder and writes `contents` to it.
+pub fn write(path: &str, contents: &[u8]) -> io::Result<()> {
+    use no_std_io2::io::Write;
+
+    let mut file = FileHandle::open(path, FileOptions::kFileWrite)?;
+    file.write_all(contents)?;
+    file.flush()
+}
+
+/// Last modification time of a file, in the Playdate's local time.
+/// Fields are ordered so the derived `Ord` is chronological.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileTime {
+    pub year: i32,
+    pub month: i32,
+    pub day: i32,
+    pub hour: i32,
+    pub minute: i32,
+    pub second: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    /// Size in bytes, 0 for directories.
+    pub size: u32,
+    pub modified: FileTime,
+}
+
+impl From<FileStat> for Metadata {
+    fn from(stat: FileStat) -> Self {
+        Self {
+            is_dir: stat.isdir != 0,
+            size: stat.size,
+            modified: FileTime {
+                year: stat.m_year,
+                month: stat.m_month,
+                day: stat.m_day,
+                hour: stat.m_hour,
+                minute: stat.m_minute,
+                second: stat.m_second,
+            },
+        }
+    }
+}
+
+/// Returns the metadata of the file or directory at `path`.
+pub fn metadata(path: &str) -> io::Result<Metadata> {
+    let path = c_path(path)?;
+    let mut stat = FileStat {
+        isdir: 0,
+        size: 0,
+        m_year: 0,
+        m_month: 0,
+        m_day: 0,
+        m_hour: 0,
+        m_minute: 0,
+        m_second: 0,
+    };
+    let result = unsafe { sys::api!(file).stat.unwrap()(path.as_ptr(), &mut stat) };
+    check(result, io::ErrorKind::NotFound)?;
+    Ok(stat.into())
+}
+
+/// Whether a file or directory exists at `path`.
+pub fn exists(path: &str) -> bool {
+    metadata(path).is_ok()
+}
+
+/// An entry returned by [`read_dir`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DirEntry {
+    /// Name relative to the listed directory, without the trailing `/` of directories.
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Iterator over the entries of a directory, see [`read_dir`].
+pub struct ReadDir(alloc::vec::IntoIter<DirEntry>);
+
+impl Iterator for ReadDir {
+    type Item = DirEntry;
+
+    fn next(&mut self) -> Option<DirEntry> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ReadDir {}
+
+/// Lists the entries of the directory at `path`, merging the data folder and the bundle.
+/// Hidden files (starting with `.`) are only included if `show_hidden` is set.
+pub fn read_dir(path: &str, show_hidden: bool) -> io::Result<ReadDir> {
+    unsafe extern "C" fn push_entry(name: *const c_char, userdata: *mut c_void) {
+        let entries = &mut *(userdata as *mut Vec<DirEntry>);
+        let name = CStr::from_ptr(name).to_string_lossy();
+        entries.push(match name.strip_suffix('/') {
+            Some(dir) => DirEntry {
+                name: dir.to_string(),
+                is_dir: true,
+            },
+            None => DirEntry {
+                name: name.into_owned(),
+                is_dir: false,
+            },
+        });
+    }
+
+    let path = c_path(path)?;
+    let mut entries: Vec<DirEntry> = Vec::new();
+    let result = unsafe {
+        sys::api!(file).listfiles.unwrap()(
+            path.as_ptr(),
+            Some(push_entry),
+            &mut entries as *mut Vec<DirEntry> as *mut c_void,
+            show_hidden as i32,
+        )
+    };
+    check(result, io::ErrorKind::NotFound)?;
+    Ok(ReadDir(entries.into_iter()))
+}
+
+/// Creates a directory in the data folder, including any missing parents.
+pub fn create_dir(path: &str) -> io::Result<()> {
+    let path = c_path(path)?;
+    let result = unsafe { sys::api!(file).mkdir.unwrap()(path.as_ptr()) };
+    check(result, io::ErrorKind::Other)
+}
+
+/// Renames a file or directory in the data folder, replacing `to` if it exists.
+pub fn rename(from: &str, to: &str) -> io::Result<()> {
+    let (from, to) = (c_path(from)?, c_path(to)?);
+    let result = unsafe { sys::api!(file).rename.unwrap()(from.as_ptr(), to.as_ptr()) };
+    check(result, io::ErrorKind::Other)
+}
+
+/// Deletes a file, or an empty directory, from the data folder.
+pub fn remove(path: &str) -> io::Result<()> {
+    unlink(path, false)
+}
+
+/// Deletes a directory and everything in it from the data folder.
+pub fn remove_dir_all(path: &str) -> io::Result<()> {
+    unlink(path, true)
+}
+
+fn unlink(path: &str, recursive: bool) -> io::Result<()> {
+    let path = c_path(path)?;
+    let result = unsafe { sys::api!(file).unlink.unwrap()(path.as_ptr(), recursive as i32) };
+    check(result, io::ErrorKind::Other)
+}
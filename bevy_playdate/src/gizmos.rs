@@ -0,0 +1,216 @@
+use crate::debug::{Debug, DebugChannel};
+use crate::view::Camera;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::SystemParam;
+use bevy_math::{Affine2, Vec2, Vec3Swizzles};
+use bevy_transform::prelude::GlobalTransform;
+use playdate::sys::ffi::LCDColor;
+
+/// System parameter for queueing immediate-mode debug shapes.
+///
+/// Shapes are only recorded while debug mode and the [`DebugChannel::Gizmos`] channel are enabled,
+/// and are drawn on top of the sprites at the end of the frame by [`crate::debug::flush_debug_commands`].
+///
+/// ```ignore
+/// fn draw_velocity(mut gizmos: Gizmos, q: Query<(&Transform, &Velocity)>) {
+///     for (transform, velocity) in &q {
+///         let start = transform.translation.truncate();
+///         gizmos.world().arrow(start, start + velocity.0, LCDColor::BLACK);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct Gizmos<'w, 's> {
+    debug: ResMut<'w, Debug>,
+    camera: Query<'w, 's, &'static GlobalTransform, With<Camera>>,
+}
+
+impl Gizmos<'_, '_> {
+    /// Draws with world-space coordinates, transformed through the active [`Camera`].
+    pub fn world(&mut self) -> GizmoPainter<'_> {
+        let view = self
+            .camera
+            .iter()
+            .next()
+            .map(|camera| {
+                let inv = camera.affine().inverse();
+                Affine2::from_cols(
+                    inv.matrix3.x_axis.xy(),
+                    inv.matrix3.y_axis.xy(),
+                    inv.translation.xy(),
+                )
+            })
+            .unwrap_or(Affine2::IDENTITY);
+
+        GizmoPainter::new(&mut self.debug, view)
+    }
+
+    /// Draws with screen-space (pixel) coordinates, ignoring the [`Camera`].
+    pub fn screen(&mut self) -> GizmoPainter<'_> {
+        GizmoPainter::new(&mut self.debug, Affine2::IDENTITY)
+    }
+}
+
+/// Records shapes into the [`Debug`] command queue through a view transform.
+///
+/// Obtained from [`Gizmos::world`] or [`Gizmos::screen`].
+pub struct GizmoPainter<'a> {
+    debug: &'a mut Debug,
+    view: Affine2,
+    line_width: i32,
+}
+
+impl<'a> GizmoPainter<'a> {
+    fn new(debug: &'a mut Debug, view: Affine2) -> Self {
+        Self {
+            debug,
+            view,
+            line_width: 1,
+        }
+    }
+
+    /// Sets the line width used by the following shapes.
+    pub fn with_line_width(mut self, line_width: i32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    fn enabled(&self) -> bool {
+        self.debug.is_enabled(DebugChannel::Gizmos)
+    }
+
+    fn project(&self, point: Vec2) -> (i32, i32) {
+        let p = self.view.transform_point2(point);
+        (p.x as i32, p.y as i32)
+    }
+
+    /// Scale applied to lengths (such as radii) by the view transform.
+    fn scale(&self) -> f32 {
+        self.view.matrix2.x_axis.length()
+    }
+
+    pub fn line(&mut self, start: Vec2, end: Vec2, color: LCDColor) -> &mut Self {
+        if self.enabled() {
+            let (start, end) = (self.project(start), self.project(end));
+            self.debug.line(start, end, self.line_width, color);
+        }
+        self
+    }
+
+    /// Draws a line from `start` to `end` with an arrow head at `end`.
+    pub fn arrow(&mut self, start: Vec2, end: Vec2, color: LCDColor) -> &mut Self {
+        const HEAD_LENGTH: f32 = 6.0;
+
+        if !self.enabled() {
+            return self;
+        }
+
+        self.line(start, end, color);
+
+        // arrow head is sized in screen space so it stays readable when zoomed
+        let (screen_start, screen_end) = (
+            self.view.transform_point2(start),
+            self.view.transform_point2(end),
+        );
+        let Some(back) = (screen_start - screen_end).try_normalize() else {
+            return self;
+        };
+        let side = back.perp();
+        let tip = (screen_end.x as i32, screen_end.y as i32);
+        for wing in [back + side * 0.5, back - side * 0.5] {
+            let wing = screen_end + wing * HEAD_LENGTH;
+            self.debug.line(tip, (wing.x as i32, wing.y as i32), self.line_width, color);
+        }
+
+        self
+    }
+
+    /// Draws an axis-aligned (in the current space) rectangle from its top left corner and size.
+    pub fn rect(&mut self, origin: Vec2, size: Vec2, color: LCDColor) -> &mut Self {
+        if !self.enabled() {
+            return self;
+        }
+
+        if self.view.matrix2 == Affine2::IDENTITY.matrix2 && self.line_width == 1 {
+            let origin = self.project(origin);
+            self.debug.rect(origin, (size.x as i32, size.y as i32), color, false);
+        } else {
+            // rotated/scaled by the camera, so it is no longer axis-aligned on screen
+            self.polyline(
+                [
+                    origin,
+                    origin + Vec2::new(size.x, 0.0),
+                    origin + size,
+                    origin + Vec2::new(0.0, size.y),
+                ],
+                true,
+                color,
+            );
+        }
+
+        self
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: LCDColor) -> &mut Self {
+        if self.enabled() {
+            let center = self.project(center);
+            let radius = (radius * self.scale()) as i32;
+            self.debug.circle(center, radius, self.line_width, color, false);
+        }
+        self
+    }
+
+    pub fn filled_circle(&mut self, center: Vec2, radius: f32, color: LCDColor) -> &mut Self {
+        if self.enabled() {
+            let center = self.project(center);
+            let radius = (radius * self.scale()) as i32;
+            self.debug.circle(center, radius, self.line_width, color, true);
+        }
+        self
+    }
+
+    /// Draws lines between each consecutive point.
+    /// If `closed`, the last point is connected back to the first.
+    pub fn polyline(
+        &mut self,
+        points: impl IntoIterator<Item = Vec2>,
+        closed: bool,
+        color: LCDColor,
+    ) -> &mut Self {
+        if self.enabled() {
+            let points: Vec<(i32, i32)> = points.into_iter().map(|p| self.project(p)).collect();
+            self.debug.polyline(points, closed, self.line_width, color);
+        }
+        self
+    }
+
+    /// Draws a parametric curve by sampling `f` at `resolution + 1` evenly spaced
+    /// `t`-values in `[0, 1]`.
+    pub fn curve(
+        &mut self,
+        f: impl Fn(f32) -> Vec2,
+        resolution: usize,
+        color: LCDColor,
+    ) -> &mut Self {
+        if self.enabled() {
+            let resolution = resolution.max(1);
+            self.polyline(
+                (0..=resolution).map(|i| f(i as f32 / resolution as f32)),
+                false,
+                color,
+            );
+        }
+        self
+    }
+
+    /// Draws text with its top left corner at `position`.
+    pub fn text(&mut self, position: Vec2, text: impl Into<String>) -> &mut Self {
+        if self.enabled() {
+            let position = self.project(position);
+            self.debug.text(position, text);
+        }
+        self
+    }
+}
@@ -5,9 +5,10 @@ use bevy_ecs::resource::Resource;
 use bevy_reflect::prelude::{Reflect, ReflectDefault};
 use bevy_ecs::reflect::ReflectResource;
 use playdate::controls::api::Cache;
-use playdate::controls::peripherals::{Accelerometer, Crank};
+use playdate::controls::peripherals::{Accelerometer, Buttons, Crank};
+use playdate::sys::ffi::PDButtons;
 
-/// Adds crank input (and once `bevy_input` is `no_std`, d-pad and buttons)
+/// Adds crank, accelerometer, d-pad and button input
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
@@ -16,11 +17,15 @@ impl Plugin for InputPlugin {
             .init_resource::<CrankInput>()
             .insert_non_send_resource(Accelerometer::Cached())
             .init_resource::<AccelerometerInput>()
+            .insert_non_send_resource(Buttons::Cached())
+            .init_resource::<ButtonInput>()
             .register_type::<CrankInput>()
             .register_type::<AccelerometerInput>()
+            .register_type::<ButtonInput>()
             .add_systems(
                 PreUpdate,
-                (crank_input_system, accelerometer_input_system).in_set(PdInputSystem),
+                (crank_input_system, accelerometer_input_system, button_input_system)
+                    .in_set(PdInputSystem),
             );
     }
 }
@@ -67,3 +72,69 @@ pub fn accelerometer_input_system(
 ) {
     (input.x, input.y, input.z) = accelerometer.get();
 }
+
+/// A button (or d-pad direction) on the Playdate.
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PdButton {
+    Left,
+    Right,
+    Up,
+    Down,
+    B,
+    A,
+}
+
+impl PdButton {
+    /// Bit of this button in the SDK's `PDButtons` bitmask.
+    pub const fn mask(self) -> u8 {
+        match self {
+            PdButton::Left => 1 << 0,
+            PdButton::Right => 1 << 1,
+            PdButton::Up => 1 << 2,
+            PdButton::Down => 1 << 3,
+            PdButton::B => 1 << 4,
+            PdButton::A => 1 << 5,
+        }
+    }
+}
+
+/// A resource reporting the current state of the d-pad and the A/B buttons.
+#[derive(Resource, Reflect, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[reflect(Resource, Default)]
+pub struct ButtonInput {
+    /// Buttons currently held down.
+    current: u8,
+    /// Buttons pressed since the last frame.
+    pushed: u8,
+    /// Buttons released since the last frame.
+    released: u8,
+}
+
+impl ButtonInput {
+    /// Whether the button is currently held down.
+    pub fn pressed(&self, button: PdButton) -> bool {
+        self.current & button.mask() != 0
+    }
+
+    /// Whether the button was pressed this frame.
+    pub fn just_pressed(&self, button: PdButton) -> bool {
+        self.pushed & button.mask() != 0
+    }
+
+    /// Whether the button was released this frame.
+    pub fn just_released(&self, button: PdButton) -> bool {
+        self.released & button.mask() != 0
+    }
+}
+
+/// Updates the [`ButtonInput`] resource with the latest [`Buttons`] inputs.
+pub fn button_input_system(mut input: ResMut<ButtonInput>, buttons: NonSend<Buttons<Cache>>) {
+    fn bits(buttons: PDButtons) -> u8 {
+        buttons.0 as u8
+    }
+
+    let state = buttons.get();
+    input.current = bits(state.current);
+    input.pushed = bits(state.pushed);
+    input.released = bits(state.released);
+}
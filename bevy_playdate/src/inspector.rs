@@ -0,0 +1,523 @@
+use crate::debug::{debug_text, in_debug};
+use crate::input::{
+    button_input_system, crank_input_system, ButtonInput, CrankInput, PdButton, PdInputSystem,
+};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_app::{App, Plugin, PostUpdate, PreUpdate};
+use bevy_ecs::entity::Entity;
+use bevy_ecs::name::Name;
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectResource};
+use bevy_reflect::{GetPath, PartialReflect, ReflectRef, TypeRegistry};
+use core::any::TypeId;
+use playdate::api;
+use playdate::graphics::color::LCDColorConst;
+use playdate::sprite::draw_sprites;
+use playdate::sys::ffi::LCDColor;
+
+/// Adds an on-device overlay for browsing and editing reflected components and resources.
+///
+/// While debug mode is enabled, hold B and press A to open or close the inspector.
+/// Turn the crank (or use up/down) to move the cursor, A to select and B to go back.
+/// Selecting a numeric field toggles editing it with the crank;
+/// left/right change how much a crank revolution changes the value.
+/// While it is open, the game sees no button presses or crank movement.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .add_systems(
+                PreUpdate,
+                capture_input
+                    .in_set(PdInputSystem)
+                    .after(button_input_system)
+                    .after(crank_input_system)
+                    .run_if(in_debug),
+            )
+            .add_systems(
+                PostUpdate,
+                inspector_system
+                    .after(draw_sprites)
+                    .after(crate::debug::flush_debug_commands)
+                    .run_if(in_debug),
+            );
+    }
+}
+
+/// Crank degrees needed to move the cursor by one row.
+const DEGREES_PER_ROW: f32 = 30.0;
+const LINE_HEIGHT: i32 = 16;
+const VISIBLE_ROWS: usize = 12;
+const PANEL_X: i32 = 4;
+const PANEL_Y: i32 = 24;
+const PANEL_WIDTH: i32 = 392;
+/// Values are truncated to this many characters so rows fit on screen.
+const MAX_VALUE_CHARS: usize = 28;
+
+#[derive(Resource)]
+pub struct Inspector {
+    /// Whether the overlay is shown.
+    pub open: bool,
+    /// How much one crank revolution changes a field being edited.
+    pub edit_scale: f32,
+    target: Option<InspectorTarget>,
+    cursor: usize,
+    editing: bool,
+    crank_accum: f32,
+    /// Edit amount not yet applied, as integer fields only change by whole steps.
+    edit_accum: f32,
+    /// Input taken from the game this frame, see [`capture_input`].
+    buttons: ButtonInput,
+    crank: CrankInput,
+}
+
+impl Default for Inspector {
+    fn default() -> Self {
+        Self {
+            open: false,
+            edit_scale: 1.0,
+            target: None,
+            cursor: 0,
+            editing: false,
+            crank_accum: 0.0,
+            edit_accum: 0.0,
+            buttons: ButtonInput::default(),
+            crank: CrankInput::default(),
+        }
+    }
+}
+
+/// What the inspector is currently showing the fields of.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InspectorTarget {
+    Entity(Entity),
+    Resources,
+}
+
+impl Inspector {
+    pub fn target(&self) -> Option<InspectorTarget> {
+        self.target
+    }
+
+    /// Shows the fields of `target`, opening the inspector if it is closed.
+    pub fn inspect(&mut self, target: InspectorTarget) {
+        self.open = true;
+        self.target = Some(target);
+        self.cursor = 0;
+        self.editing = false;
+    }
+
+    fn back(&mut self) {
+        if self.editing {
+            self.editing = false;
+            self.edit_accum = 0.0;
+        } else if self.target.is_some() {
+            self.target = None;
+            self.cursor = 0;
+        } else {
+            self.open = false;
+        }
+    }
+
+    /// Moves the cursor by whole rows of accumulated crank rotation.
+    fn scroll(&mut self, crank: &CrankInput, buttons: &ButtonInput, len: usize) {
+        self.crank_accum += crank.change;
+        let mut rows = (self.crank_accum / DEGREES_PER_ROW) as isize;
+        self.crank_accum -= rows as f32 * DEGREES_PER_ROW;
+
+        if buttons.just_pressed(PdButton::Down) {
+            rows += 1;
+        }
+        if buttons.just_pressed(PdButton::Up) {
+            rows -= 1;
+        }
+
+        if len == 0 {
+            self.cursor = 0;
+        } else {
+            self.cursor = self.cursor.saturating_add_signed(rows).min(len - 1);
+        }
+    }
+}
+
+/// A single line of the inspector.
+struct Row {
+    depth: usize,
+    label: String,
+    value: Option<String>,
+    action: RowAction,
+}
+
+enum RowAction {
+    None,
+    Inspect(InspectorTarget),
+    Edit(FieldPath),
+}
+
+/// Where an editable field lives, so it can be re-borrowed mutably.
+struct FieldPath {
+    owner: FieldOwner,
+    path: String,
+}
+
+#[derive(Copy, Clone)]
+enum FieldOwner {
+    Component(Entity, TypeId),
+    Resource(TypeId),
+}
+
+/// Opens or closes the inspector with B + A. While it is open, moves the button presses and
+/// crank movement into it, so the game, and anything else reading [`ButtonInput`], doesn't react to them.
+pub fn capture_input(
+    mut inspector: ResMut<Inspector>,
+    mut buttons: ResMut<ButtonInput>,
+    mut crank: ResMut<CrankInput>,
+) {
+    let toggled = buttons.pressed(PdButton::B) && buttons.just_pressed(PdButton::A);
+    if toggled {
+        inspector.open = !inspector.open;
+    }
+
+    if inspector.open && !toggled {
+        inspector.buttons = *buttons;
+        inspector.crank = *crank;
+    } else {
+        inspector.buttons = ButtonInput::default();
+        inspector.crank = CrankInput::default();
+    }
+
+    if inspector.open || toggled {
+        *buttons = ButtonInput::default();
+        crank.change = 0.0;
+    }
+}
+
+/// Exclusive system that handles inspector input and draws the overlay.
+pub fn inspector_system(world: &mut World) {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    world.resource_scope(|world, mut inspector: Mut<Inspector>| {
+        if !inspector.open {
+            return;
+        }
+        let (buttons, crank) = (inspector.buttons, inspector.crank);
+
+        // entities can be despawned while they are being inspected
+        if let Some(InspectorTarget::Entity(e)) = inspector.target {
+            if world.get_entity(e).is_err() {
+                inspector.target = None;
+                inspector.cursor = 0;
+            }
+        }
+
+        let rows = match inspector.target {
+            None => entity_rows(world),
+            Some(target) => field_rows(world, &registry, target),
+        };
+
+        if inspector.editing {
+            if let Some(Row { action: RowAction::Edit(field), .. }) = rows.get(inspector.cursor) {
+                if buttons.just_pressed(PdButton::Right) {
+                    inspector.edit_scale *= 10.0;
+                }
+                if buttons.just_pressed(PdButton::Left) {
+                    inspector.edit_scale /= 10.0;
+                }
+                inspector.edit_accum += crank.change / 360.0 * inspector.edit_scale;
+                if inspector.edit_accum != 0.0 {
+                    edit_field(world, &registry, field, &mut inspector.edit_accum);
+                }
+            } else {
+                inspector.editing = false;
+            }
+        } else {
+            inspector.scroll(&crank, &buttons, rows.len());
+        }
+
+        if buttons.just_pressed(PdButton::A) {
+            match rows.get(inspector.cursor).map(|row| &row.action) {
+                Some(RowAction::Inspect(target)) => inspector.inspect(*target),
+                Some(RowAction::Edit(_)) => {
+                    inspector.editing = !inspector.editing;
+                    inspector.edit_accum = 0.0;
+                }
+                _ => {}
+            }
+        } else if buttons.just_pressed(PdButton::B) {
+            inspector.back();
+        }
+
+        draw_rows(&inspector, &rows);
+    });
+}
+
+fn entity_rows(world: &mut World) -> Vec<Row> {
+    let mut rows = Vec::new();
+    rows.push(Row {
+        depth: 0,
+        label: "Resources".to_string(),
+        value: None,
+        action: RowAction::Inspect(InspectorTarget::Resources),
+    });
+
+    let mut named: Vec<(Entity, String)> = world
+        .query::<(Entity, &Name)>()
+        .iter(world)
+        .map(|(e, name)| (e, name.as_str().to_string()))
+        .collect();
+    named.sort_by_key(|(e, _)| *e);
+
+    rows.extend(named.into_iter().map(|(e, name)| Row {
+        depth: 0,
+        label: name,
+        value: Some(format!("{e}")),
+        action: RowAction::Inspect(InspectorTarget::Entity(e)),
+    }));
+
+    rows
+}
+
+fn field_rows(world: &World, registry: &TypeRegistry, target: InspectorTarget) -> Vec<Row> {
+    let mut rows = Vec::new();
+
+    match target {
+        InspectorTarget::Entity(e) => {
+            let entity = world.entity(e);
+            for component_id in entity.archetype().components() {
+                let Some(info) = world.components().get_info(component_id) else {
+                    continue;
+                };
+                let reflected = info.type_id().and_then(|type_id| {
+                    let registration = registry.get(type_id)?;
+                    let value = registration.data::<ReflectComponent>()?.reflect(entity)?;
+                    Some((type_id, registration.type_info().type_path_table().short_path(), value))
+                });
+
+                match reflected {
+                    Some((type_id, name, value)) => flatten(
+                        value.as_partial_reflect(),
+                        0,
+                        name.to_string(),
+                        String::new(),
+                        FieldOwner::Component(e, type_id),
+                        &mut rows,
+                    ),
+                    None => rows.push(Row {
+                        depth: 0,
+                        label: short_name(info.name()).to_string(),
+                        value: Some("(not reflected)".to_string()),
+                        action: RowAction::None,
+                    }),
+                }
+            }
+        }
+        InspectorTarget::Resources => {
+            for registration in registry.iter() {
+                let Some(reflect_resource) = registration.data::<ReflectResource>() else {
+                    continue;
+                };
+                let Ok(value) = reflect_resource.reflect(world) else {
+                    continue;
+                };
+                flatten(
+                    value.as_partial_reflect(),
+                    0,
+                    registration.type_info().type_path_table().short_path().to_string(),
+                    String::new(),
+                    FieldOwner::Resource(registration.type_id()),
+                    &mut rows,
+                );
+            }
+        }
+    }
+
+    rows
+}
+
+/// Pushes a row for `value` and (recursively) one for each of its fields.
+fn flatten(
+    value: &dyn PartialReflect,
+    depth: usize,
+    label: String,
+    path: String,
+    owner: FieldOwner,
+    rows: &mut Vec<Row>,
+) {
+    let child = |rows: &mut Vec<Row>, field: &dyn PartialReflect, label: String, access: String| {
+        flatten(field, depth + 1, label, format!("{path}{access}"), owner, rows);
+    };
+
+    match value.reflect_ref() {
+        ReflectRef::Struct(s) => {
+            rows.push(Row { depth, label, value: None, action: RowAction::None });
+            for i in 0..s.field_len() {
+                let name = s.name_at(i).unwrap_or_default();
+                child(rows, s.field_at(i).unwrap(), name.to_string(), format!(".{name}"));
+            }
+        }
+        ReflectRef::TupleStruct(s) => {
+            rows.push(Row { depth, label, value: None, action: RowAction::None });
+            for i in 0..s.field_len() {
+                child(rows, s.field(i).unwrap(), format!("{i}"), format!(".{i}"));
+            }
+        }
+        ReflectRef::Tuple(t) => {
+            rows.push(Row { depth, label, value: None, action: RowAction::None });
+            for i in 0..t.field_len() {
+                child(rows, t.field(i).unwrap(), format!("{i}"), format!(".{i}"));
+            }
+        }
+        ReflectRef::List(l) => {
+            rows.push(Row { depth, label, value: Some(format!("[{}]", l.len())), action: RowAction::None });
+            for (i, item) in l.iter().enumerate() {
+                child(rows, item, format!("[{i}]"), format!("[{i}]"));
+            }
+        }
+        ReflectRef::Array(a) => {
+            rows.push(Row { depth, label, value: Some(format!("[{}]", a.len())), action: RowAction::None });
+            for (i, item) in a.iter().enumerate() {
+                child(rows, item, format!("[{i}]"), format!("[{i}]"));
+            }
+        }
+        ReflectRef::Enum(e) => {
+            rows.push(Row {
+                depth,
+                label,
+                value: Some(e.variant_name().to_string()),
+                action: RowAction::None,
+            });
+            for i in 0..e.field_len() {
+                let (name, access) = match e.name_at(i) {
+                    Some(name) => (name.to_string(), format!(".{name}")),
+                    None => (format!("{i}"), format!(".{i}")),
+                };
+                child(rows, e.field_at(i).unwrap(), name, access);
+            }
+        }
+        _ => {
+            let action = if is_numeric(value) && !path.is_empty() {
+                RowAction::Edit(FieldPath { owner, path })
+            } else {
+                RowAction::None
+            };
+            rows.push(Row {
+                depth,
+                label,
+                value: Some(truncate(format!("{value:?}"))),
+                action,
+            });
+        }
+    }
+}
+
+fn edit_field(world: &mut World, registry: &TypeRegistry, field: &FieldPath, amount: &mut f32) {
+    let value = match field.owner {
+        FieldOwner::Component(e, type_id) => {
+            let Some(reflect_component) = registry.get_type_data::<ReflectComponent>(type_id) else {
+                return;
+            };
+            let Ok(entity) = world.get_entity_mut(e) else {
+                return;
+            };
+            reflect_component.reflect_mut(entity)
+        }
+        FieldOwner::Resource(type_id) => {
+            let Some(reflect_resource) = registry.get_type_data::<ReflectResource>(type_id) else {
+                return;
+            };
+            reflect_resource.reflect_mut(world).ok()
+        }
+    };
+
+    let Some(mut value) = value else {
+        return;
+    };
+    if let Ok(field) = value.reflect_path_mut(field.path.as_str()) {
+        nudge(field, amount);
+    }
+}
+
+fn is_numeric(value: &dyn PartialReflect) -> bool {
+    macro_rules! any_of {
+        ($($ty:ty),*) => { false $(|| value.try_downcast_ref::<$ty>().is_some())* };
+    }
+    any_of!(f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize)
+}
+
+/// Adds `amount` to a numeric field, leaving in it whatever wasn't applied.
+/// Integers are changed by whole steps, so the fraction is kept for later.
+fn nudge(value: &mut dyn PartialReflect, amount: &mut f32) {
+    macro_rules! float {
+        ($($ty:ty),*) => {$(
+            if let Some(v) = value.try_downcast_mut::<$ty>() {
+                *v += *amount as $ty;
+                *amount = 0.0;
+                return;
+            }
+        )*};
+    }
+    macro_rules! int {
+        ($($ty:ty),*) => {$(
+            if let Some(v) = value.try_downcast_mut::<$ty>() {
+                let steps = *amount as i64;
+                *amount -= steps as f32;
+                *v = (*v as i128 + steps as i128).clamp(<$ty>::MIN as i128, <$ty>::MAX as i128) as $ty;
+                return;
+            }
+        )*};
+    }
+    float!(f32, f64);
+    int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+    *amount = 0.0;
+}
+
+fn short_name(name: &str) -> &str {
+    let without_generics = name.split('<').next().unwrap_or(name);
+    without_generics.rsplit("::").next().unwrap_or(without_generics)
+}
+
+fn truncate(mut s: String) -> String {
+    if let Some((i, _)) = s.char_indices().nth(MAX_VALUE_CHARS) {
+        s.truncate(i);
+        s.push_str("...");
+    }
+    s
+}
+
+fn draw_rows(inspector: &Inspector, rows: &[Row]) {
+    let first = inspector
+        .cursor
+        .saturating_sub(VISIBLE_ROWS / 2)
+        .min(rows.len().saturating_sub(VISIBLE_ROWS));
+    let shown = &rows[first..rows.len().min(first + VISIBLE_ROWS)];
+    let height = (shown.len().max(1) as i32) * LINE_HEIGHT + 4;
+
+    unsafe {
+        api!(graphics).fillRect.unwrap()(PANEL_X, PANEL_Y, PANEL_WIDTH, height, LCDColor::WHITE);
+        api!(graphics).drawRect.unwrap()(PANEL_X, PANEL_Y, PANEL_WIDTH, height, LCDColor::BLACK);
+    }
+
+    for (i, row) in shown.iter().enumerate() {
+        let index = first + i;
+        let marker = match (index == inspector.cursor, inspector.editing) {
+            (true, true) => "*",
+            (true, false) => ">",
+            _ => " ",
+        };
+        let indent = "  ".repeat(row.depth);
+        let text = match &row.value {
+            Some(value) => format!("{marker}{indent}{}: {value}", row.label),
+            None => format!("{marker}{indent}{}", row.label),
+        };
+
+        debug_text(&text, PANEL_X + 4, PANEL_Y + 2 + i as i32 * LINE_HEIGHT);
+    }
+
+    if inspector.editing {
+        debug_text(&format!("x{}", inspector.edit_scale), PANEL_X + PANEL_WIDTH - 60, PANEL_Y + 2);
+    }
+}
@@ -2,28 +2,83 @@ use alloc::boxed::Box;
 use alloc::collections::BinaryHeap;
 use alloc::vec;
 use alloc::vec::Vec;
-use bevy_ecs::prelude::{In, Local, Mut, Resource};
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::{
+    Component, DetectChangesMut, Event, In, IntoSystemConfigs, Local, Mut, Query, Res, Resource,
+    SystemSet,
+};
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_reflect::Reflect;
 use bevy_ecs::system::{BoxedSystem, IntoSystem, System, SystemId};
 use bevy_ecs::world::World;
 use core::any::Any;
 use core::borrow::Borrow;
 use core::cmp::Ordering;
+use core::fmt;
 use core::marker::PhantomData;
 use core::ops::DerefMut;
+use core::time::Duration;
 use derive_more::From;
-use hashbrown::HashMap;
+use bevy_transform::TransformSystem;
+use hashbrown::{HashMap, HashSet};
 use playdate::println;
+use crate::time::Time;
+
+pub mod tasks;
 
 type JobId = usize;
 
+/// Runs [`Jobs`] every frame after [`Update`](bevy_app::Update), within [`Jobs::budget`].
+pub struct JobsPlugin;
+
+impl Plugin for JobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Jobs>()
+            .register_type::<JobProgress>()
+            .add_systems(
+                PostUpdate,
+                (Jobs::run_jobs, update_job_progress)
+                    .chain()
+                    .in_set(JobSystems)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Label for the system that runs [`Jobs`].
+#[derive(Debug, PartialEq, Eq, Clone, Hash, SystemSet)]
+pub struct JobSystems;
+
 #[derive(Resource)]
 pub struct Jobs {
-    /// Minimum number of jobs to run per frame
+    /// Minimum number of job steps to run per frame, even if over budget
     pub min_jobs: usize,
+    /// Time per frame to keep running job steps for.
+    ///
+    /// Keep this below the frame time of the target refresh rate
+    /// (20 ms at 50 fps) minus the time the rest of the frame takes.
+    pub budget: Duration,
+    /// How long a finished job's result is kept for [`Jobs::try_claim`]
+    /// before it is dropped. `None` keeps results until they are claimed.
+    ///
+    /// Whether a job succeeded is kept this long too, even once its result is claimed,
+    /// so [`JobProgress`] can report it. `None` keeps it forever.
+    pub result_timeout: Option<Duration>,
     id_gen: JobId,
     unstarted: Vec<UnstartedJob>,
+    /// Jobs waiting for another job to succeed, see [`Jobs::then`].
+    waiting: Vec<WaitingJob>,
+    /// Jobs that were given a dependent job, which can only happen once.
+    depended: HashSet<JobId>,
     jobs: BinaryHeap<RunningJob>,
-    finished: HashMap<JobId, Result<Box<dyn Any>, Box<dyn Any>>>,
+    finished: HashMap<JobId, FinishedJob>,
+    /// Whether each finished job succeeded and the start of the frame it finished in,
+    /// kept after its result is claimed.
+    outcomes: HashMap<JobId, (bool, Duration)>,
+    /// Results of jobs awaited by async tasks, see [`Jobs::join`].
+    awaited: HashMap<JobId, tasks::JobSlot>,
+    /// Systems of cancelled jobs, unregistered on the next run.
+    cancelled: Vec<SystemId<In<Box<dyn Any>>, ErasedWorkStatus>>,
 }
 
 unsafe impl Send for Jobs {}
@@ -33,10 +88,17 @@ impl Default for Jobs {
     fn default() -> Self {
         Self {
             min_jobs: 5,
+            budget: Duration::from_millis(5),
+            result_timeout: Some(Duration::from_secs(10)),
             id_gen: 0,
             unstarted: vec![],
+            waiting: vec![],
+            depended: Default::default(),
             jobs: BinaryHeap::new(),
             finished: Default::default(),
+            outcomes: Default::default(),
+            awaited: Default::default(),
+            cancelled: vec![],
         }
     }
 }
@@ -49,23 +111,79 @@ impl Jobs {
         job: &JobHandle<Work, Success, Error>,
     ) -> Option<JobStatusRef<Work, Success, Error>> {
         if let Some(job) = self.finished.get(&job.id) {
-            return Some(match job {
+            return Some(match &job.result {
                 Ok(val) => JobStatusRef::Success(val.downcast_ref().unwrap()),
                 Err(val) => JobStatusRef::Error(val.downcast_ref().unwrap()),
             });
         }
 
         if let Some(job) = self.unstarted.iter().find(|j| j.id == job.id) {
-            return Some(JobStatusRef::InProgress(job.work.downcast_ref().unwrap()));
+            return Some(JobStatusRef::NotStarted(job.work.downcast_ref().unwrap()));
+        }
+
+        if self.waiting.iter().any(|w| w.job.id == job.id) {
+            return Some(JobStatusRef::Waiting);
         }
 
         if let Some(job) = self.jobs.iter().find(|j| j.id == job.id) {
-            return Some(JobStatusRef::InProgress(job.work.downcast_ref().unwrap()));
+            let work = job.work.downcast_ref().unwrap();
+            return Some(if job.started {
+                JobStatusRef::InProgress(work)
+            } else {
+                JobStatusRef::NotStarted(work)
+            });
         }
 
         None
     }
 
+    /// How far along `job` is, from 0 to 1.
+    ///
+    /// `None` if the job hasn't reported progress with [`WorkResult::Progress`] yet,
+    /// or is unknown. Finished jobs report 1.
+    pub fn fraction<Work: Any, Success: Any, Error: Any>(
+        &self,
+        job: &JobHandle<Work, Success, Error>,
+    ) -> Option<f32> {
+        self.fraction_of(job.id)
+    }
+
+    fn fraction_of(&self, id: JobId) -> Option<f32> {
+        if self.finished.contains_key(&id) || self.outcomes.contains_key(&id) {
+            return Some(1.0);
+        }
+
+        self.jobs.iter().find(|j| j.id == id).and_then(|j| j.fraction)
+    }
+
+    fn state_of(&self, id: JobId) -> JobState {
+        if let Some(job) = self.finished.get(&id) {
+            return match job.result {
+                Ok(_) => JobState::Succeeded,
+                Err(_) => JobState::Failed,
+            };
+        }
+
+        if self.unstarted.iter().any(|j| j.id == id) {
+            return JobState::NotStarted;
+        }
+
+        if self.waiting.iter().any(|w| w.job.id == id) {
+            return JobState::Waiting;
+        }
+
+        match self.jobs.iter().find(|j| j.id == id) {
+            Some(job) if job.started => JobState::InProgress,
+            Some(_) => JobState::NotStarted,
+            // the result may have been claimed, taken by an observer or a dependent job
+            None => match self.outcomes.get(&id) {
+                Some((true, _)) => JobState::Succeeded,
+                Some((false, _)) => JobState::Failed,
+                None => JobState::Unknown,
+            },
+        }
+    }
+
     fn next_id(&mut self) -> JobId {
         let out = self.id_gen;
         self.id_gen += 1;
@@ -78,15 +196,8 @@ impl Jobs {
         initial: Work,
         job: impl IntoSystem<In<Work>, WorkResult<Work, Success, Error>, M>,
     ) -> JobHandle<Work, Success, Error> {
-        let job = IntoSystem::into_system(pipe_any.pipe(job).map(ErasedWorkStatus::from));
-
         let id = self.next_id();
-        let job = UnstartedJob {
-            priority,
-            work: Box::new(initial),
-            id,
-            job: Box::new(job),
-        };
+        let job = Self::unstarted_job(id, priority, Box::new(initial), job);
 
         self.unstarted.push(job);
 
@@ -96,13 +207,171 @@ impl Jobs {
         }
     }
 
+    /// Adds a job that starts once `after` succeeds, with its success value as the initial work.
+    ///
+    /// If `after` already succeeded, this job starts right away. If `after` fails or is cancelled
+    /// later, this job is cancelled too. The success value of `after` is moved into this job,
+    /// so it can't be claimed and no [`JobFinished`] is triggered for it.
+    ///
+    /// Fails without adding the job if `after` already has a dependent job, as only one can
+    /// receive its success value, if it already failed, or if its result is gone.
+    pub fn then<Work: Any, Success: Any, Error: Any, Success2: Any, Error2: Any, M>(
+        &mut self,
+        after: &JobHandle<Work, Success, Error>,
+        priority: isize,
+        job: impl IntoSystem<In<Success>, WorkResult<Success, Success2, Error2>, M>,
+    ) -> Result<JobHandle<Success, Success2, Error2>, ThenError> {
+        if self.depended.contains(&after.id) {
+            return Err(ThenError::AlreadyHasDependent);
+        }
+
+        let work = match self.finished.remove(&after.id) {
+            Some(FinishedJob { result: Ok(val), .. }) => Some(val),
+            // keep the error for `try_claim`
+            Some(failed) => {
+                self.finished.insert(after.id, failed);
+                return Err(ThenError::Failed);
+            }
+            None if self.is_pending(after.id) => None,
+            None => {
+                return Err(match self.outcomes.get(&after.id) {
+                    Some((false, _)) => ThenError::Failed,
+                    _ => ThenError::NoResult,
+                })
+            }
+        };
+        self.depended.insert(after.id);
+
+        let id = self.next_id();
+        match work {
+            Some(val) => {
+                let job = Self::unstarted_job(id, priority, val, job);
+                self.unstarted.push(job);
+            }
+            None => {
+                // placeholder work, replaced by the success value of `after`
+                let job = Self::unstarted_job(id, priority, Box::new(()), job);
+                self.waiting.push(WaitingJob { after: after.id, job });
+            }
+        }
+
+        Ok(JobHandle {
+            id,
+            _phantom_data: Default::default(),
+        })
+    }
+
+    /// Whether the job is yet to start, waiting, or running.
+    fn is_pending(&self, id: JobId) -> bool {
+        self.unstarted.iter().any(|j| j.id == id)
+            || self.waiting.iter().any(|w| w.job.id == id)
+            || self.jobs.iter().any(|j| j.id == id)
+    }
+
+    fn unstarted_job<Work: Any, Success: Any, Error: Any, M>(
+        id: JobId,
+        priority: isize,
+        work: Box<dyn Any>,
+        job: impl IntoSystem<In<Work>, WorkResult<Work, Success, Error>, M>,
+    ) -> UnstartedJob {
+        let job = IntoSystem::into_system(pipe_any.pipe(job).map(ErasedWorkStatus::from));
+
+        UnstartedJob {
+            priority,
+            work,
+            id,
+            job: Box::new(job),
+            on_finish: finish_job::<Success, Error>,
+        }
+    }
+
+    /// Stops a job and drops its work or result. Jobs waiting on it are cancelled too.
+    ///
+    /// Returns `false` if the job was unknown, already claimed or already cancelled.
+    pub fn cancel<Work: Any, Success: Any, Error: Any>(
+        &mut self,
+        job: &JobHandle<Work, Success, Error>,
+    ) -> bool {
+        self.cancel_id(job.id)
+    }
+
+    fn cancel_id(&mut self, id: JobId) -> bool {
+        let mut found = self.finished.remove(&id).is_some();
+        self.outcomes.remove(&id);
+
+        if let Some(i) = self.unstarted.iter().position(|j| j.id == id) {
+            self.unstarted.remove(i);
+            found = true;
+        }
+
+        if let Some(i) = self.waiting.iter().position(|w| w.job.id == id) {
+            self.waiting.remove(i);
+            found = true;
+        }
+
+        if self.jobs.iter().any(|j| j.id == id) {
+            let cancelled = &mut self.cancelled;
+            self.jobs.retain(|j| {
+                if j.id == id {
+                    cancelled.push(j.job);
+                }
+                j.id != id
+            });
+            found = true;
+        }
+
+        self.cancel_awaited(id);
+        self.cancel_dependents(id);
+
+        found
+    }
+
+    fn cancel_dependents(&mut self, id: JobId) {
+        if let Some(i) = self.waiting.iter().position(|w| w.after == id) {
+            let dependent = self.waiting.remove(i);
+            self.cancel_awaited(dependent.job.id);
+            self.cancel_dependents(dependent.job.id);
+        }
+    }
+
+    /// Unregisters systems of cancelled jobs and drops results that were never claimed.
+    fn cleanup(&mut self, world: &mut World, now: Duration) {
+        for system in self.cancelled.drain(..) {
+            let _ = world.unregister_system(system);
+        }
+
+        if let Some(timeout) = self.result_timeout {
+            self.finished
+                .retain(|_, job| now.saturating_sub(job.finished_at) < timeout);
+            self.outcomes
+                .retain(|_, (_, finished_at)| now.saturating_sub(*finished_at) < timeout);
+        }
+
+        // forget jobs that can't be depended on anymore anyway
+        let depended = core::mem::take(&mut self.depended);
+        self.depended = depended
+            .into_iter()
+            .filter(|&id| {
+                self.is_pending(id) || self.finished.contains_key(&id) || self.outcomes.contains_key(&id)
+            })
+            .collect();
+    }
+
     fn understarted_jobs(&mut self) -> (&mut Vec<UnstartedJob>, &mut BinaryHeap<RunningJob>) {
         (&mut self.unstarted, &mut self.jobs)
     }
 
-    /// System to run jobs
+    /// System to run jobs.
+    ///
+    /// Runs at least [`Jobs::min_jobs`] steps, then keeps running steps
+    /// until [`Jobs::budget`] is used up or every job has finished or skipped this frame.
     pub fn run_jobs(world: &mut World, mut skip_buffer: Local<Vec<RunningJob>>) {
-        world.resource_scope(|world, mut jobs: Mut<Jobs>| {
+        let start = world.resource::<Time>().now();
+        tasks::begin_frame();
+
+        let finished = world.resource_scope(|world, mut jobs: Mut<Jobs>| {
+            jobs.cleanup(world, start);
+
             let (unstarted, jbs) = jobs.understarted_jobs();
             for job in unstarted.drain(..) {
                 let j = world.register_boxed_system(job.job);
@@ -112,47 +381,113 @@ impl Jobs {
                     work: job.work,
                     id: job.id,
                     job: j,
+                    on_finish: job.on_finish,
+                    started: false,
+                    fraction: None,
                 });
             }
 
-            for _ in 0..jobs.min_jobs {
+            let mut finished = Vec::new();
+            let mut steps = 0;
+            loop {
+                if steps >= jobs.min_jobs
+                    && world.resource::<Time>().now().saturating_sub(start) >= jobs.budget
+                {
+                    break;
+                }
                 let Some(mut job) = jobs.jobs.pop() else {
                     break;
                 };
+                steps += 1;
+                job.started = true;
 
-                match world.run_system_with(job.job, job.work).unwrap() {
+                let status = world.run_system_with(job.job, job.work).unwrap();
+                if let Some(fraction) = tasks::take_progress() {
+                    job.fraction = Some(fraction.clamp(0.0, 1.0));
+                }
+                match status {
                     ErasedWorkStatus::Continue(val) => {
                         job.work = val;
                         jobs.jobs.push(job);
                     }
+                    ErasedWorkStatus::Progress(val, fraction) => {
+                        job.work = val;
+                        job.fraction = Some(fraction.clamp(0.0, 1.0));
+                        jobs.jobs.push(job);
+                    }
                     ErasedWorkStatus::Skip(val) => {
                         job.work = val;
                         skip_buffer.deref_mut().push(job);
                     }
                     ErasedWorkStatus::Success(val) => {
-                        jobs.finished.insert(job.id, Ok(val));
+                        finished.push((job.id, job.on_finish, Ok(val)));
                         world.unregister_system(job.job).unwrap();
                     }
                     ErasedWorkStatus::Error(val) => {
-                        jobs.finished.insert(job.id, Err(val));
+                        finished.push((job.id, job.on_finish, Err(val)));
                         world.unregister_system(job.job).unwrap();
                     }
                 }
             }
 
-            // Todo: do jobs while time still left
-
             for job in skip_buffer.deref_mut().drain(..) {
                 jobs.jobs.push(job);
             }
+
+            finished
         });
+
+        // outside of the resource scope so observers can add or cancel jobs
+        for (id, on_finish, result) in finished {
+            Self::finish(world, id, on_finish, result, start);
+        }
+    }
+
+    /// Hands the result of a finished job to the task awaiting it or to its dependent job,
+    /// otherwise triggers [`JobFinished`] and keeps the result if no observer took it.
+    fn finish(world: &mut World, id: JobId, on_finish: FinishFn, result: ErasedResult, now: Duration) {
+        let mut jobs = world.resource_mut::<Jobs>();
+        jobs.outcomes.insert(id, (result.is_ok(), now));
+        let result = match jobs.complete_awaited(id, result) {
+            // handed to the awaiting task, so there is nothing to pass to dependents
+            Ok(()) => {
+                jobs.cancel_dependents(id);
+                return;
+            }
+            Err(result) => result,
+        };
+        let result = match result {
+            Ok(val) => match jobs.waiting.iter().position(|w| w.after == id) {
+                Some(i) => {
+                    let mut dependent = jobs.waiting.remove(i).job;
+                    dependent.work = val;
+                    jobs.unstarted.push(dependent);
+                    return;
+                }
+                None => Ok(val),
+            },
+            Err(val) => {
+                jobs.cancel_dependents(id);
+                Err(val)
+            }
+        };
+
+        if let Some(result) = on_finish(world, id, result) {
+            world.resource_mut::<Jobs>().finished.insert(
+                id,
+                FinishedJob {
+                    result,
+                    finished_at: now,
+                },
+            );
+        }
     }
 
     pub fn try_claim<Work: Any, Success: Any, Error: Any>(
         &mut self,
         job: &JobHandle<Work, Success, Error>,
     ) -> Option<Result<Success, Error>> {
-        match self.finished.remove(&job.id) {
+        match self.finished.remove(&job.id).map(|job| job.result) {
             None => None,
             Some(Ok(val)) => Some(Ok(*val.downcast().unwrap())),
             Some(Err(val)) => Some(Err(*val.downcast().unwrap())),
@@ -160,6 +495,106 @@ impl Jobs {
     }
 }
 
+type ErasedResult = Result<Box<dyn Any>, Box<dyn Any>>;
+
+/// Triggers [`JobFinished`] for a job, returning the result if no observer took it.
+type FinishFn = fn(&mut World, JobId, ErasedResult) -> Option<ErasedResult>;
+
+fn finish_job<Success: Any, Error: Any>(
+    world: &mut World,
+    id: JobId,
+    result: ErasedResult,
+) -> Option<ErasedResult> {
+    let result = match result {
+        Ok(val) => Ok(*val.downcast::<Success>().unwrap()),
+        Err(val) => Err(*val.downcast::<Error>().unwrap()),
+    };
+
+    let mut event = JobFinished::<Success, Error> {
+        id,
+        result: Some(result),
+    };
+    world.trigger_ref(&mut event);
+
+    event.result.map(|result| match result {
+        Ok(val) => Ok(Box::new(val) as Box<dyn Any>),
+        Err(val) => Err(Box::new(val) as Box<dyn Any>),
+    })
+}
+
+/// Triggered when a job succeeds or fails.
+///
+/// An observer can [`take`](JobFinished::take) the result,
+/// otherwise it is kept for [`Jobs::try_claim`].
+///
+/// ```ignore
+/// world.add_observer(|mut trigger: Trigger<JobFinished<Level, LoadError>>| {
+///     if let Some(Ok(level)) = trigger.event_mut().take() {
+///         // ...
+///     }
+/// });
+/// ```
+#[derive(Event)]
+pub struct JobFinished<Success, Error> {
+    id: JobId,
+    result: Option<Result<Success, Error>>,
+}
+
+// SAFETY: The Playdate is single-threaded.
+// The event trait requires Send + Sync
+unsafe impl<Success, Error> Send for JobFinished<Success, Error> {}
+unsafe impl<Success, Error> Sync for JobFinished<Success, Error> {}
+
+impl<Success, Error> JobFinished<Success, Error> {
+    /// Whether this event is for `job`.
+    pub fn is<Work>(&self, job: &JobHandle<Work, Success, Error>) -> bool {
+        self.id == job.id
+    }
+
+    /// The result, if no other observer has taken it yet.
+    pub fn result(&self) -> Option<&Result<Success, Error>> {
+        self.result.as_ref()
+    }
+
+    /// Takes the result, so it is not kept for [`Jobs::try_claim`].
+    pub fn take(&mut self) -> Option<Result<Success, Error>> {
+        self.result.take()
+    }
+}
+
+struct FinishedJob {
+    result: ErasedResult,
+    /// Start of the frame the job finished in.
+    finished_at: Duration,
+}
+
+/// Why [`Jobs::then`] couldn't add a dependent job.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ThenError {
+    /// The job already has a dependent job, and only one can receive its success value.
+    AlreadyHasDependent,
+    /// The job already failed, so the dependent job would never start.
+    Failed,
+    /// The job's result was claimed or dropped, or the job was cancelled or is unknown.
+    NoResult,
+}
+
+impl fmt::Display for ThenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThenError::AlreadyHasDependent => write!(f, "the job already has a dependent job"),
+            ThenError::Failed => write!(f, "the job already failed"),
+            ThenError::NoResult => write!(f, "the job has no result to depend on"),
+        }
+    }
+}
+
+struct WaitingJob {
+    /// Job that must succeed before this one starts.
+    after: JobId,
+    job: UnstartedJob,
+}
+
 fn pipe_any<T: Any>(In(val): In<Box<dyn Any>>) -> T {
     *val.downcast().unwrap()
 }
@@ -175,7 +610,10 @@ trait UntypedJob {
 }
 
 pub enum JobStatusRef<'a, Work, Success, Error> {
+    /// Added, but hasn't run yet.
     NotStarted(&'a Work),
+    /// Waiting for the job it depends on to succeed, see [`Jobs::then`].
+    Waiting,
     InProgress(&'a Work),
     Success(&'a Success),
     Error(&'a Error),
@@ -186,11 +624,20 @@ pub struct JobHandle<Work, Success, Error> {
     _phantom_data: PhantomData<(Work, Success, Error)>,
 }
 
+impl<Work, Success, Error> Clone for JobHandle<Work, Success, Error> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Work, Success, Error> Copy for JobHandle<Work, Success, Error> {}
+
 pub struct UnstartedJob {
     priority: isize,
     work: Box<dyn Any>,
     id: usize,
     job: BoxedSystem<In<Box<dyn Any>>, ErasedWorkStatus>,
+    on_finish: FinishFn,
 }
 
 pub struct RunningJob {
@@ -198,6 +645,11 @@ pub struct RunningJob {
     work: Box<dyn Any>,
     id: usize,
     job: SystemId<In<Box<dyn Any>>, ErasedWorkStatus>,
+    on_finish: FinishFn,
+    /// Whether the job has run at least once.
+    started: bool,
+    /// Last fraction reported with [`WorkResult::Progress`].
+    fraction: Option<f32>,
 }
 
 unsafe impl Send for RunningJob {}
@@ -216,9 +668,12 @@ impl PartialOrd<Self> for RunningJob {
     }
 }
 
+/// Higher priority jobs run first, then older jobs.
 impl Ord for RunningJob {
     fn cmp(&self, other: &Self) -> Ordering {
-        other.priority.cmp(&self.priority)
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.id.cmp(&self.id))
     }
 }
 
@@ -226,6 +681,9 @@ pub enum WorkResult<TWork, TSuccess, TError> {
     ///
     Continue(TWork),
 
+    /// Same as [`WorkResult::Continue`], also reporting how far along the job is (0 to 1).
+    Progress(TWork, f32),
+
     Skip(TWork),
 
     Success(TSuccess),
@@ -237,6 +695,8 @@ pub enum ErasedWorkStatus {
     ///
     Continue(Box<dyn Any>),
 
+    Progress(Box<dyn Any>, f32),
+
     Skip(Box<dyn Any>),
 
     Success(Box<dyn Any>),
@@ -250,6 +710,7 @@ impl<TWork: Any, TSuccess: Any, TError: Any> From<WorkResult<TWork, TSuccess, TE
     fn from(value: WorkResult<TWork, TSuccess, TError>) -> Self {
         match value {
             WorkResult::Continue(val) => ErasedWorkStatus::Continue(Box::new(val)),
+            WorkResult::Progress(val, fraction) => ErasedWorkStatus::Progress(Box::new(val), fraction),
             WorkResult::Skip(val) => ErasedWorkStatus::Skip(Box::new(val)),
             WorkResult::Success(val) => ErasedWorkStatus::Success(Box::new(val)),
             WorkResult::Error(val) => ErasedWorkStatus::Error(Box::new(val)),
@@ -257,6 +718,67 @@ impl<TWork: Any, TSuccess: Any, TError: Any> From<WorkResult<TWork, TSuccess, TE
     }
 }
 
+/// Untyped state of a job, see [`JobProgress`].
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum JobState {
+    /// Added, but hasn't run yet.
+    #[default]
+    NotStarted,
+    /// Waiting for the job it depends on to succeed.
+    Waiting,
+    InProgress,
+    /// Succeeded, even if its result was already claimed.
+    Succeeded,
+    Failed,
+    /// Cancelled, or finished longer ago than [`Jobs::result_timeout`].
+    Unknown,
+}
+
+/// Tracks the progress of a job, e.g. on a loading screen entity to draw a progress bar.
+///
+/// Updated every frame after the jobs run.
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct JobProgress {
+    job: JobId,
+    pub state: JobState,
+    /// How far along the job is, from 0 to 1, if it reports its progress.
+    pub fraction: Option<f32>,
+}
+
+impl JobProgress {
+    pub fn new<Work, Success, Error>(job: &JobHandle<Work, Success, Error>) -> Self {
+        Self {
+            job: job.id,
+            state: JobState::NotStarted,
+            fraction: None,
+        }
+    }
+
+    /// Whether the job has succeeded or failed.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, JobState::Succeeded | JobState::Failed)
+    }
+}
+
+/// Updates every [`JobProgress`] from [`Jobs`].
+pub fn update_job_progress(jobs: Res<Jobs>, mut q_progress: Query<&mut JobProgress>) {
+    for mut progress in &mut q_progress {
+        let state = jobs.state_of(progress.job);
+        // keep the last known values once the outcome expires or the job is cancelled
+        if state == JobState::Unknown && progress.is_finished() {
+            continue;
+        }
+
+        let fraction = jobs.fraction_of(progress.job);
+        progress.set_if_neq(JobProgress {
+            job: progress.job,
+            state,
+            fraction,
+        });
+    }
+}
+
 fn test() {
     let mut jobs = Jobs::default();
 
@@ -270,3 +792,121 @@ fn system_test(mut i: In<Vec<i32>>, world: &mut World) -> WorkResult<Vec<i32>, (
 
     WorkResult::Continue(i.0)
 }
+
+#[cfg(test)]
+mod test {
+    use crate::jobs::{ErasedResult, JobHandle, JobState, Jobs, ThenError, WorkResult};
+    use alloc::boxed::Box;
+    use bevy_ecs::prelude::In;
+    use bevy_ecs::world::World;
+    use core::any::Any;
+    use core::time::Duration;
+
+    fn count(In(n): In<i32>) -> WorkResult<i32, i32, &'static str> {
+        WorkResult::Continue(n + 1)
+    }
+
+    fn double(In(n): In<i32>) -> WorkResult<i32, i32, ()> {
+        WorkResult::Success(n * 2)
+    }
+
+    fn setup() -> (World, JobHandle<i32, i32, &'static str>) {
+        let mut world = World::new();
+        world.init_resource::<Jobs>();
+        let job = world.resource_mut::<Jobs>().add(0, 0, count);
+        (world, job)
+    }
+
+    /// Finishes `job` as if it had returned `result` from a step.
+    fn finish<Work, Success: Any, Error: Any>(
+        world: &mut World,
+        job: &JobHandle<Work, Success, Error>,
+        result: Result<Success, Error>,
+    ) {
+        let mut jobs = world.resource_mut::<Jobs>();
+        let i = jobs.unstarted.iter().position(|j| j.id == job.id).unwrap();
+        let on_finish = jobs.unstarted.remove(i).on_finish;
+        let result: ErasedResult = match result {
+            Ok(val) => Ok(Box::new(val)),
+            Err(val) => Err(Box::new(val)),
+        };
+        Jobs::finish(world, job.id, on_finish, result, Duration::ZERO);
+    }
+
+    /// The initial work of an unstarted job.
+    fn work_of<Work: Any + Copy, Success, Error>(
+        world: &World,
+        job: &JobHandle<Work, Success, Error>,
+    ) -> Work {
+        let jobs = world.resource::<Jobs>();
+        let job = jobs.unstarted.iter().find(|j| j.id == job.id).unwrap();
+        *job.work.downcast_ref().unwrap()
+    }
+
+    #[test]
+    fn test_then_pending() {
+        let (mut world, first) = setup();
+        let mut jobs = world.resource_mut::<Jobs>();
+        let second = jobs.then(&first, 0, double).unwrap();
+        assert_eq!(jobs.then(&first, 0, double).err(), Some(ThenError::AlreadyHasDependent));
+        assert_eq!(jobs.state_of(second.id), JobState::Waiting);
+
+        finish(&mut world, &first, Ok(21));
+        assert_eq!(work_of(&world, &second), 21);
+        // the value went to the dependent, not to `try_claim`
+        let mut jobs = world.resource_mut::<Jobs>();
+        assert!(jobs.try_claim(&first).is_none());
+        assert_eq!(jobs.state_of(first.id), JobState::Succeeded);
+    }
+
+    #[test]
+    fn test_then_pending_fails() {
+        let (mut world, first) = setup();
+        let second = world.resource_mut::<Jobs>().then(&first, 0, double).unwrap();
+
+        finish(&mut world, &first, Err("broken"));
+        let mut jobs = world.resource_mut::<Jobs>();
+        assert_eq!(jobs.state_of(second.id), JobState::Unknown);
+        assert_eq!(jobs.try_claim(&first), Some(Err("broken")));
+    }
+
+    #[test]
+    fn test_then_succeeded() {
+        let (mut world, first) = setup();
+        finish(&mut world, &first, Ok(4));
+
+        let mut jobs = world.resource_mut::<Jobs>();
+        let second = jobs.then(&first, 0, double).unwrap();
+        // the result was moved into `second`, but `first` still has its dependent
+        assert_eq!(jobs.then(&first, 0, double).err(), Some(ThenError::AlreadyHasDependent));
+        assert_eq!(work_of(&world, &second), 4);
+    }
+
+    #[test]
+    fn test_then_failed() {
+        let (mut world, first) = setup();
+        finish(&mut world, &first, Err("broken"));
+
+        let mut jobs = world.resource_mut::<Jobs>();
+        assert_eq!(jobs.then(&first, 0, double).err(), Some(ThenError::Failed));
+        // the error is still there to claim, and stays a failure once claimed
+        assert_eq!(jobs.try_claim(&first), Some(Err("broken")));
+        assert_eq!(jobs.then(&first, 0, double).err(), Some(ThenError::Failed));
+        assert!(jobs.unstarted.is_empty() && jobs.waiting.is_empty());
+    }
+
+    #[test]
+    fn test_then_claimed_or_cancelled() {
+        let (mut world, first) = setup();
+        finish(&mut world, &first, Ok(4));
+
+        let mut jobs = world.resource_mut::<Jobs>();
+        assert_eq!(jobs.try_claim(&first), Some(Ok(4)));
+        assert_eq!(jobs.then(&first, 0, double).err(), Some(ThenError::NoResult));
+
+        let cancelled = jobs.add(0, 0, count);
+        assert!(jobs.cancel(&cancelled));
+        assert_eq!(jobs.then(&cancelled, 0, double).err(), Some(ThenError::NoResult));
+        assert!(jobs.unstarted.is_empty() && jobs.waiting.is_empty());
+    }
+}
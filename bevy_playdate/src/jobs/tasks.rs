@@ -0,0 +1,433 @@
+//! A single-threaded executor for `async` tasks, run as [`Jobs`] within the same time budget.
+//!
+//! ```ignore
+//! fn load_level(mut jobs: ResMut<Jobs>) {
+//!     let tileset = jobs.add(0, TilesetLoader::new("level.tsx"), load_tileset_step);
+//!     let tileset = jobs.join(&tileset);
+//!
+//!     jobs.spawn(0, async move {
+//!         let bytes = read_file("level.txt").await?;
+//!         let tileset = tileset.await;
+//!         set_progress(0.5);
+//!         next_frame().await;
+//!         with_world(|world| world.spawn(Name::new("Level")).id());
+//!         Ok(())
+//!     });
+//! }
+//! ```
+
+use super::{ErasedResult, JobHandle, JobId, Jobs, WorkResult};
+use crate::fs::Location;
+use crate::vfs::{OpenMode, Vfs, VfsFile};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bevy_ecs::prelude::In;
+use bevy_ecs::world::World;
+use core::any::Any;
+use core::cell::{RefCell, UnsafeCell};
+use core::convert::Infallible;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use no_std_io2::io::{self, Read};
+
+/// A spawned `async` task. This is the work type of its [`JobHandle`].
+pub struct Task<T>(Pin<Box<dyn Future<Output = T>>>);
+
+impl Jobs {
+    /// Runs `future` as a job, polling it until it completes.
+    ///
+    /// The task is polled again in the same frame (if there is budget left)
+    /// after [`yield_now`] or a partial [`read_file`], otherwise on the next frame.
+    pub fn spawn<T: Any>(
+        &mut self,
+        priority: isize,
+        future: impl Future<Output = T> + 'static,
+    ) -> JobHandle<Task<T>, T, Infallible> {
+        self.add(priority, Task(Box::pin(future)), poll_task::<T>)
+    }
+
+    /// Returns a future that completes with the result of `job`,
+    /// or `None` if it is cancelled or was already claimed.
+    ///
+    /// The result is handed to the future instead of [`JobFinished`](super::JobFinished) observers
+    /// or [`Jobs::try_claim`], and jobs added with [`Jobs::then`] on `job` are cancelled.
+    pub fn join<Work: Any, Success: Any, Error: Any>(
+        &mut self,
+        job: &JobHandle<Work, Success, Error>,
+    ) -> JobFuture<Success, Error> {
+        let slot = Rc::new(RefCell::new(SlotState::Pending));
+
+        if let Some(finished) = self.finished.remove(&job.id) {
+            *slot.borrow_mut() = SlotState::Done(finished.result);
+        } else if !self.is_pending(job.id) {
+            *slot.borrow_mut() = SlotState::Cancelled;
+        } else if let Some(previous) = self.awaited.insert(job.id, slot.clone()) {
+            // only the latest join gets the result
+            *previous.borrow_mut() = SlotState::Cancelled;
+        }
+
+        JobFuture {
+            slot,
+            _phantom_data: Default::default(),
+        }
+    }
+
+    /// Hands `result` to the future awaiting job `id`, or returns it if there is none.
+    pub(super) fn complete_awaited(
+        &mut self,
+        id: JobId,
+        result: ErasedResult,
+    ) -> Result<(), ErasedResult> {
+        match self.awaited.remove(&id) {
+            Some(slot) => {
+                *slot.borrow_mut() = SlotState::Done(result);
+                Ok(())
+            }
+            None => Err(result),
+        }
+    }
+
+    pub(super) fn cancel_awaited(&mut self, id: JobId) {
+        if let Some(slot) = self.awaited.remove(&id) {
+            *slot.borrow_mut() = SlotState::Cancelled;
+        }
+    }
+}
+
+pub(super) type JobSlot = Rc<RefCell<SlotState>>;
+
+pub(super) enum SlotState {
+    Pending,
+    Done(ErasedResult),
+    Cancelled,
+}
+
+/// Future returned by [`Jobs::join`].
+pub struct JobFuture<Success, Error> {
+    slot: JobSlot,
+    _phantom_data: PhantomData<(Success, Error)>,
+}
+
+impl<Success: Any, Error: Any> Future for JobFuture<Success, Error> {
+    type Output = Option<Result<Success, Error>>;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.borrow_mut();
+        match core::mem::replace(&mut *slot, SlotState::Cancelled) {
+            SlotState::Pending => {
+                *slot = SlotState::Pending;
+                // checked again next frame
+                Poll::Pending
+            }
+            SlotState::Done(Ok(val)) => Poll::Ready(Some(Ok(*val.downcast().unwrap()))),
+            SlotState::Done(Err(val)) => Poll::Ready(Some(Err(*val.downcast().unwrap()))),
+            SlotState::Cancelled => Poll::Ready(None),
+        }
+    }
+}
+
+/// State shared between the executor and the running task.
+struct TaskContext {
+    /// World of the system polling the task, null outside of a poll.
+    world: *mut World,
+    /// Progress reported with [`set_progress`] during the current poll.
+    progress: Option<f32>,
+    /// Incremented every time the jobs run.
+    frame: u64,
+}
+
+struct GlobalContext(UnsafeCell<TaskContext>);
+
+// SAFETY: The Playdate is single-threaded,
+// and no reference into the context outlives the functions below.
+unsafe impl Sync for GlobalContext {}
+
+static CONTEXT: GlobalContext = GlobalContext(UnsafeCell::new(TaskContext {
+    world: null_mut(),
+    progress: None,
+    frame: 0,
+}));
+
+/// Set when the task being polled wakes itself, so it can be polled again this frame.
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+fn with_context<T>(f: impl FnOnce(&mut TaskContext) -> T) -> T {
+    // SAFETY: see `GlobalContext`
+    f(unsafe { &mut *CONTEXT.0.get() })
+}
+
+/// Called once per frame before the jobs run.
+pub(super) fn begin_frame() {
+    with_context(|c| c.frame += 1);
+}
+
+/// Takes the progress reported by the job that just ran.
+pub(super) fn take_progress() -> Option<f32> {
+    with_context(|c| c.progress.take())
+}
+
+/// Gives the running task access to the [`World`].
+///
+/// # Panics
+/// Panics if called outside of a task spawned with [`Jobs::spawn`], or from inside `f`.
+/// [`Jobs`] itself can't be accessed this way, as it is in use while tasks run.
+pub fn with_world<R>(f: impl FnOnce(&mut World) -> R) -> R {
+    let world = with_context(|c| core::mem::replace(&mut c.world, null_mut()));
+    assert!(!world.is_null(), "with_world called outside of a task");
+
+    // SAFETY: the pointer is only set while the executor lends its `&mut World` to the task,
+    // and it is taken out of the context so it can't be aliased by a nested call.
+    let out = f(unsafe { &mut *world });
+
+    with_context(|c| c.world = world);
+    out
+}
+
+/// Reports how far along the running job is, from 0 to 1.
+/// See [`super::JobProgress`].
+pub fn set_progress(fraction: f32) {
+    with_context(|c| c.progress = Some(fraction));
+}
+
+/// Waits until the next frame.
+pub fn next_frame() -> NextFrame {
+    NextFrame { frame: None }
+}
+
+pub struct NextFrame {
+    frame: Option<u64>,
+}
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let now = with_context(|c| c.frame);
+        match self.frame {
+            Some(frame) if now > frame => Poll::Ready(()),
+            Some(_) => Poll::Pending,
+            None => {
+                self.frame = Some(now);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Lets other jobs run, continuing in the same frame if there is budget left.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Reads a whole file from the pdx bundle through the world's [`Vfs`], one chunk per poll.
+///
+/// # Panics
+/// Panics if awaited outside of a task spawned with [`Jobs::spawn`], see [`with_world`].
+pub fn read_file(path: impl Into<String>) -> ReadFile {
+    ReadFile {
+        path: path.into(),
+        file: None,
+        buf: Vec::new(),
+    }
+}
+
+pub struct ReadFile {
+    path: String,
+    file: Option<VfsFile>,
+    buf: Vec<u8>,
+}
+
+impl ReadFile {
+    const CHUNK_SIZE: usize = 4096;
+}
+
+impl Future for ReadFile {
+    type Output = io::Result<Vec<u8>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let file = match &mut this.file {
+            Some(file) => file,
+            None => {
+                let vfs = with_world(|world| Vfs::of(world));
+                match vfs.open(&this.path, OpenMode::Read(Location::Bundle)) {
+                    Ok(file) => this.file.insert(file),
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+        };
+
+        let len = this.buf.len();
+        this.buf.resize(len + Self::CHUNK_SIZE, 0);
+        match file.read(&mut this.buf[len..]) {
+            Ok(0) => {
+                this.buf.truncate(len);
+                this.file = None;
+                Poll::Ready(Ok(core::mem::take(&mut this.buf)))
+            }
+            Ok(n) => {
+                this.buf.truncate(len + n);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn poll_task<T: Any>(
+    In(mut task): In<Task<T>>,
+    world: &mut World,
+) -> WorkResult<Task<T>, T, Infallible> {
+    WOKEN.store(false, Ordering::Relaxed);
+    with_context(|c| c.world = world);
+
+    // SAFETY: the vtable functions ignore the data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+    let poll = task.0.as_mut().poll(&mut Context::from_waker(&waker));
+
+    with_context(|c| c.world = null_mut());
+
+    match poll {
+        Poll::Ready(val) => WorkResult::Success(val),
+        Poll::Pending if WOKEN.load(Ordering::Relaxed) => WorkResult::Continue(task),
+        Poll::Pending => WorkResult::Skip(task),
+    }
+}
+
+/// Waker that marks the task being polled as ready to be polled again.
+/// There is only ever one task being polled, so it doesn't need to know which one.
+static VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |data| RawWaker::new(data, &VTABLE),
+    |_| WOKEN.store(true, Ordering::Relaxed),
+    |_| WOKEN.store(true, Ordering::Relaxed),
+    |_| {},
+);
+
+#[cfg(test)]
+mod test {
+    use crate::jobs::tasks::{
+        begin_frame, next_frame, poll_task, read_file, with_world, yield_now, Task,
+    };
+    use crate::jobs::{Jobs, WorkResult};
+    use crate::vfs::{MemoryFs, Vfs};
+    use alloc::boxed::Box;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use bevy_ecs::prelude::In;
+    use bevy_ecs::world::World;
+    use core::any::Any;
+    use core::cell::RefCell;
+    use core::future::Future;
+
+    fn task<T>(future: impl Future<Output = T> + 'static) -> Task<T> {
+        Task(Box::pin(future))
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Step {
+        /// Polled again in the same frame.
+        Continue,
+        /// Polled again next frame.
+        Skip,
+        Done,
+    }
+
+    fn poll<T: Any>(world: &mut World, task: &mut Option<Task<T>>) -> (Step, Option<T>) {
+        match poll_task(In(task.take().unwrap()), world) {
+            WorkResult::Continue(t) => {
+                *task = Some(t);
+                (Step::Continue, None)
+            }
+            WorkResult::Skip(t) => {
+                *task = Some(t);
+                (Step::Skip, None)
+            }
+            WorkResult::Success(val) => (Step::Done, Some(val)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn count(In(n): In<i32>) -> WorkResult<i32, i32, ()> {
+        WorkResult::Continue(n + 1)
+    }
+
+    // a single test, as the executor's context is global and tests run in parallel
+    #[test]
+    fn test_tasks() {
+        let mut world = World::new();
+        let memory = MemoryFs::default();
+        memory.insert_bundle("level.txt", "hello");
+        world.insert_resource(Vfs::new(memory));
+
+        // yield_now continues in the same frame, next_frame waits for the jobs to run again
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut running = Some(task({
+            let log = log.clone();
+            async move {
+                log.borrow_mut().push("start");
+                yield_now().await;
+                log.borrow_mut().push("yielded");
+                next_frame().await;
+                log.borrow_mut().push("next frame");
+                if with_world(|world| world.contains_resource::<Vfs>()) {
+                    log.borrow_mut().push("world");
+                }
+                read_file("level.txt").await
+            }
+        }));
+        assert_eq!(poll(&mut world, &mut running).0, Step::Continue);
+        assert_eq!(*log.borrow(), ["start"]);
+        assert_eq!(poll(&mut world, &mut running).0, Step::Skip);
+        assert_eq!(poll(&mut world, &mut running).0, Step::Skip);
+        assert_eq!(*log.borrow(), ["start", "yielded"]);
+
+        begin_frame();
+        // the file is read in chunks, waking the task until it reaches the end
+        assert_eq!(poll(&mut world, &mut running).0, Step::Continue);
+        assert_eq!(*log.borrow(), ["start", "yielded", "next frame", "world"]);
+        let (step, read) = poll(&mut world, &mut running);
+        assert_eq!(step, Step::Done);
+        assert_eq!(read.unwrap().unwrap(), b"hello");
+
+        let (step, read) = poll(&mut world, &mut Some(task(read_file("missing.txt"))));
+        assert_eq!(step, Step::Done);
+        assert!(read.unwrap().is_err());
+
+        // join waits for the job's result, and gets nothing once it is cancelled
+        let mut jobs = Jobs::default();
+        let job = jobs.add(0, 0, count);
+        let mut joined = Some(task(jobs.join(&job)));
+        assert_eq!(poll(&mut world, &mut joined).0, Step::Skip);
+        assert!(jobs.complete_awaited(job.id, Ok(Box::new(5))).is_ok());
+        assert_eq!(poll(&mut world, &mut joined), (Step::Done, Some(Some(Ok(5)))));
+
+        let job = jobs.add(0, 0, count);
+        let mut joined = Some(task(jobs.join(&job)));
+        jobs.cancel(&job);
+        assert_eq!(poll(&mut world, &mut joined), (Step::Done, Some(None)));
+    }
+}
@@ -1,5 +1,6 @@
 #![no_std]
 
+pub mod collider;
 pub mod debug;
 pub mod input;
 pub mod jobs;
@@ -10,8 +11,16 @@ pub mod event;
 pub mod angle;
 mod utils;
 pub mod file;
+pub mod fs;
+pub mod gizmos;
+pub mod inspector;
+pub mod save;
+pub mod scene;
+pub mod vfs;
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use bevy_app::{App, Plugin};
 use bevy_ecs::prelude::*;
@@ -25,8 +34,12 @@ impl Plugin for DefaultPlugins {
             sprite::SpritePlugin,
             time::TimePlugin,
             debug::DebugPlugin,
+            collider::ColliderPlugin,
+            inspector::InspectorPlugin,
+            jobs::JobsPlugin,
             view::ViewPlugin,
             bevy_transform::TransformPlugin,
-        ));
+        ))
+        .init_resource::<vfs::Vfs>();
     }
 }
\ No newline at end of file
@@ -0,0 +1,334 @@
+//! Persists selected reflected resources and components to the data folder,
+//! through the world's [`Vfs`].
+//!
+//! ```ignore
+//! app.add_plugins(SavePlugin::new("save.json", 2))
+//!     .save_resource::<Settings>()
+//!     .save_component::<LevelProgress>()
+//!     .add_save_migration(1, |save| {
+//!         // version 2 renamed `volume` to `music_volume`
+//!         let settings = save["resources"]["game::Settings"].as_object_mut().ok_or("no settings")?;
+//!         let volume = settings.remove("volume").unwrap_or_default();
+//!         settings.insert("music_volume".into(), volume);
+//!         Ok(())
+//!     });
+//!
+//! commands.spawn((Persistent::new("player"), LevelProgress::default()));
+//! ```
+
+use crate::event::SystemEvent;
+use crate::fs::Location;
+use crate::vfs::Vfs;
+use crate::{error, info};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_app::{App, Plugin, PostStartup};
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectResource};
+use bevy_reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use bevy_reflect::{GetTypeRegistration, Reflect, TypeRegistry};
+use core::any::TypeId;
+use no_std_io2::io;
+use serde::de::DeserializeSeed;
+use serde_json::{Map, Value};
+
+/// Loads the save file after startup and saves it when the game is terminated or the device is locked.
+///
+/// Choose what gets saved with [`SaveAppExt`].
+pub struct SavePlugin {
+    /// Path of the save file in the data folder.
+    pub path: String,
+    /// Current format version, see [`SaveAppExt::add_save_migration`].
+    pub version: u32,
+}
+
+impl SavePlugin {
+    pub fn new(path: impl Into<String>, version: u32) -> Self {
+        Self {
+            path: path.into(),
+            version,
+        }
+    }
+}
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SaveSettings {
+            path: self.path.clone(),
+            version: self.version,
+            auto_save: true,
+            resources: Vec::new(),
+            components: Vec::new(),
+            migrations: BTreeMap::new(),
+        })
+        .init_resource::<Vfs>()
+        .register_type::<Persistent>()
+        .add_observer(save_on_request)
+        .add_observer(load_on_request)
+        .add_observer(auto_save)
+        .add_systems(PostStartup, |mut commands: Commands| commands.trigger(LoadGame));
+    }
+}
+
+/// Upgrades a save from one version to the next by editing its JSON in place.
+pub type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// What is saved, and where. Inserted by [`SavePlugin`].
+#[derive(Resource)]
+pub struct SaveSettings {
+    pub path: String,
+    pub version: u32,
+    /// Saves on [`SystemEvent::Terminate`] and [`SystemEvent::Lock`].
+    pub auto_save: bool,
+    resources: Vec<TypeId>,
+    components: Vec<TypeId>,
+    /// Migrations keyed by the version they upgrade from.
+    migrations: BTreeMap<u32, Migration>,
+}
+
+pub trait SaveAppExt {
+    /// Saves resource `R`, and replaces it with the saved value on load.
+    fn save_resource<R: Resource + Reflect + GetTypeRegistration>(&mut self) -> &mut Self;
+
+    /// Saves component `C` of every entity with a [`Persistent`] key.
+    fn save_component<C: Component + Reflect + GetTypeRegistration>(&mut self) -> &mut Self;
+
+    /// Registers a migration from save version `from` to `from + 1`.
+    ///
+    /// Loading an older save runs each migration up to [`SavePlugin::version`] in order,
+    /// and fails if one is missing.
+    fn add_save_migration(&mut self, from: u32, migration: Migration) -> &mut Self;
+}
+
+impl SaveAppExt for App {
+    fn save_resource<R: Resource + Reflect + GetTypeRegistration>(&mut self) -> &mut Self {
+        self.register_type::<R>();
+        self.world_mut()
+            .resource_mut::<SaveSettings>()
+            .resources
+            .push(TypeId::of::<R>());
+        self
+    }
+
+    fn save_component<C: Component + Reflect + GetTypeRegistration>(&mut self) -> &mut Self {
+        self.register_type::<C>();
+        self.world_mut()
+            .resource_mut::<SaveSettings>()
+            .components
+            .push(TypeId::of::<C>());
+        self
+    }
+
+    fn add_save_migration(&mut self, from: u32, migration: Migration) -> &mut Self {
+        self.world_mut()
+            .resource_mut::<SaveSettings>()
+            .migrations
+            .insert(from, migration);
+        self
+    }
+}
+
+/// Marks an entity whose saved components are stored under `key`.
+///
+/// On load, components are inserted into the entity with the same key,
+/// or a new one is spawned if there is none.
+#[derive(Component, Reflect, Clone, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct Persistent(pub String);
+
+impl Persistent {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(key.into())
+    }
+}
+
+/// Trigger to save the game.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct SaveGame;
+
+/// Trigger to load the save file, if there is one.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct LoadGame;
+
+fn save_on_request(_trigger: Trigger<SaveGame>, mut commands: Commands) {
+    commands.queue(|world: &mut World| match save_game(world) {
+        Ok(()) => info!("saved game"),
+        Err(e) => error!("failed to save game: {e}"),
+    });
+}
+
+fn load_on_request(_trigger: Trigger<LoadGame>, mut commands: Commands) {
+    commands.queue(|world: &mut World| match load_game(world) {
+        Ok(true) => info!("loaded game"),
+        Ok(false) => {}
+        Err(e) => error!("failed to load game: {e}"),
+    });
+}
+
+fn auto_save(trigger: Trigger<SystemEvent>, settings: Res<SaveSettings>, mut commands: Commands) {
+    if settings.auto_save && matches!(trigger.event(), SystemEvent::Terminate | SystemEvent::Lock) {
+        commands.trigger(SaveGame);
+    }
+}
+
+pub(crate) fn invalid_data(msg: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Writes the saved resources and components to [`SaveSettings::path`].
+///
+/// The save is written to a temporary file first and then renamed over the old one,
+/// so an interrupted write can't corrupt it.
+pub fn save_game(world: &World) -> io::Result<()> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let settings = world.resource::<SaveSettings>();
+
+    let mut resources = Map::new();
+    for &type_id in &settings.resources {
+        let (path, reflect) = registration::<ReflectResource>(&registry, type_id)?;
+        // resources that don't exist yet are skipped rather than saved as missing
+        let Ok(value) = reflect.reflect(world) else {
+            continue;
+        };
+        resources.insert(path.into(), serialize(value.as_partial_reflect(), &registry)?);
+    }
+
+    let mut entities = Map::new();
+    for entity in world.iter_entities() {
+        let Some(key) = entity.get::<Persistent>() else {
+            continue;
+        };
+        let mut components = Map::new();
+        for &type_id in &settings.components {
+            let (path, reflect) = registration::<ReflectComponent>(&registry, type_id)?;
+            if let Some(value) = reflect.reflect(entity) {
+                components.insert(path.into(), serialize(value.as_partial_reflect(), &registry)?);
+            }
+        }
+        entities.insert(key.0.clone(), Value::Object(components));
+    }
+
+    let mut save = Map::new();
+    save.insert("version".into(), settings.version.into());
+    save.insert("resources".into(), Value::Object(resources));
+    save.insert("entities".into(), Value::Object(entities));
+    let bytes = serde_json::to_vec(&Value::Object(save)).map_err(invalid_data)?;
+
+    let vfs = Vfs::of(world);
+    let temp = format!("{}.tmp", settings.path);
+    vfs.write(&temp, &bytes)?;
+    vfs.rename(&temp, &settings.path)
+}
+
+/// Loads [`SaveSettings::path`], migrating it to the current version.
+/// Returns `false` if there is no save file.
+pub fn load_game(world: &mut World) -> io::Result<bool> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let settings = world.resource::<SaveSettings>();
+    let vfs = Vfs::of(world);
+    if !vfs.exists(&settings.path) {
+        return Ok(false);
+    }
+    let bytes = vfs.read(&settings.path, Location::Data)?;
+    let mut save: Value = serde_json::from_slice(&bytes).map_err(invalid_data)?;
+    migrate(settings, &mut save)?;
+
+    if let Some(resources) = save.get_mut("resources").and_then(Value::as_object_mut) {
+        for (type_path, value) in core::mem::take(resources) {
+            let (registration, reflect) = by_path::<ReflectResource>(&registry, &type_path)?;
+            let value = TypedReflectDeserializer::new(registration, &registry)
+                .deserialize(value)
+                .map_err(|e| invalid_data(format!("{type_path}: {e}")))?;
+            reflect.apply_or_insert(world, value.as_partial_reflect(), &registry);
+        }
+    }
+
+    if let Some(entities) = save.get_mut("entities").and_then(Value::as_object_mut) {
+        let mut existing: BTreeMap<String, Entity> = world
+            .query::<(Entity, &Persistent)>()
+            .iter(world)
+            .map(|(e, key)| (key.0.clone(), e))
+            .collect();
+
+        for (key, components) in core::mem::take(entities) {
+            let Value::Object(components) = components else {
+                return Err(invalid_data(format!("entity {key} is not an object")));
+            };
+            let entity = match existing.remove(&key) {
+                Some(e) => e,
+                None => world.spawn(Persistent(key)).id(),
+            };
+            for (type_path, value) in components {
+                let (registration, reflect) = by_path::<ReflectComponent>(&registry, &type_path)?;
+                let value = TypedReflectDeserializer::new(registration, &registry)
+                    .deserialize(value)
+                    .map_err(|e| invalid_data(format!("{type_path}: {e}")))?;
+                reflect.apply_or_insert(&mut world.entity_mut(entity), value.as_partial_reflect(), &registry);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+/// Runs the migrations from the save's version up to the current one.
+fn migrate(settings: &SaveSettings, save: &mut Value) -> io::Result<()> {
+    let version = save
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| invalid_data("save has no version"))? as u32;
+
+    if version > settings.version {
+        return Err(invalid_data(format!(
+            "save version {version} is newer than {}",
+            settings.version
+        )));
+    }
+
+    for from in version..settings.version {
+        let migration = settings
+            .migrations
+            .get(&from)
+            .ok_or_else(|| invalid_data(format!("no migration from save version {from}")))?;
+        migration(save).map_err(|e| invalid_data(format!("migrating from version {from}: {e}")))?;
+    }
+    save["version"] = settings.version.into();
+
+    Ok(())
+}
+
+fn serialize(value: &dyn bevy_reflect::PartialReflect, registry: &TypeRegistry) -> io::Result<Value> {
+    serde_json::to_value(TypedReflectSerializer::new(value, registry)).map_err(invalid_data)
+}
+
+/// Looks up the type path and reflection data `D` of a type added with [`SaveAppExt`].
+fn registration<D: bevy_reflect::TypeData>(
+    registry: &TypeRegistry,
+    type_id: TypeId,
+) -> io::Result<(&'static str, &D)> {
+    let registration = registry
+        .get(type_id)
+        .ok_or_else(|| invalid_data("saved type is not registered"))?;
+    let data = registration
+        .data::<D>()
+        .ok_or_else(|| invalid_data(format!("{} is missing #[reflect(...)]", registration.type_info().type_path())))?;
+    Ok((registration.type_info().type_path(), data))
+}
+
+pub(crate) fn by_path<'a, D: bevy_reflect::TypeData>(
+    registry: &'a TypeRegistry,
+    type_path: &str,
+) -> io::Result<(&'a bevy_reflect::TypeRegistration, &'a D)> {
+    let registration = registry
+        .get_with_type_path(type_path)
+        .ok_or_else(|| invalid_data(format!("unknown saved type {type_path}")))?;
+    let data = registration
+        .data::<D>()
+        .ok_or_else(|| invalid_data(format!("{type_path} is missing #[reflect(...)]")))?;
+    Ok((registration, data))
+}
@@ -0,0 +1,303 @@
+//! Saves entities with all of their reflected components, and spawns them back.
+//!
+//! Scenes are a compact binary format ([postcard]): the list of saved entities in order,
+//! each with its id when saved and its components as type paths and serialized values.
+//! On load each one is spawned as a new entity, and every [`Entity`] inside the components
+//! (including in lists and enums, e.g. `Segment::start_joint` or `SegmentConnection::id`)
+//! is remapped to the new ids.
+//!
+//! ```ignore
+//! let bytes = scene::save_scene(world, world.query_filtered::<Entity, Or<(With<Segment>, With<Joint>)>>().iter(world))?;
+//! Vfs::of(world).write("track.scn", &bytes)?;
+//!
+//! // shipped in the bundle
+//! let spawned = scene::load_scene_file(world, "tracks/loop.scn", Location::Bundle)?;
+//! ```
+
+use crate::fs::Location;
+use crate::save::{by_path, invalid_data};
+use crate::vfs::Vfs;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+use bevy_ecs::world::World;
+use bevy_reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use bevy_reflect::{PartialReflect, ReflectMut};
+use hashbrown::HashMap;
+use no_std_io2::io;
+use serde::de::DeserializeSeed;
+
+/// An entity's id when it was saved, and its components' type paths and values.
+type SavedEntity = (u64, Vec<(String, Vec<u8>)>);
+
+/// Serializes `entities` with every component that is registered with `#[reflect(Component)]`.
+/// Components without reflection data are skipped.
+pub fn save_scene(world: &World, entities: impl IntoIterator<Item = Entity>) -> io::Result<Vec<u8>> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let mut scene: Vec<SavedEntity> = Vec::new();
+    for entity in entities {
+        let entity_ref = world
+            .get_entity(entity)
+            .map_err(|_| invalid_data(format!("entity {entity} does not exist")))?;
+
+        let mut components = Vec::new();
+        for component_id in entity_ref.archetype().components() {
+            let Some(registration) = world
+                .components()
+                .get_info(component_id)
+                .and_then(|info| info.type_id())
+                .and_then(|type_id| registry.get(type_id))
+            else {
+                continue;
+            };
+            let Some(value) = registration
+                .data::<ReflectComponent>()
+                .and_then(|reflect| reflect.reflect(entity_ref))
+            else {
+                continue;
+            };
+            let type_path = registration.type_info().type_path();
+            let serializer = TypedReflectSerializer::new(value.as_partial_reflect(), &registry);
+            let value = postcard::to_allocvec(&serializer).map_err(|e| invalid_data(format!("{type_path}: {e}")))?;
+            components.push((type_path.to_string(), value));
+        }
+        scene.push((entity.to_bits(), components));
+    }
+
+    postcard::to_allocvec(&scene).map_err(invalid_data)
+}
+
+/// Spawns the entities of a scene saved with [`save_scene`], returning them in the order they were saved.
+///
+/// Fails if a component references an entity that is not part of the scene.
+/// Nothing is spawned if loading fails.
+pub fn load_scene(world: &mut World, bytes: &[u8]) -> io::Result<Vec<Entity>> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let scene: Vec<SavedEntity> = postcard::from_bytes(bytes).map_err(invalid_data)?;
+
+    // deserialize everything first so a bad scene doesn't leave half of it spawned
+    let mut loaded = Vec::with_capacity(scene.len());
+    for (id, components) in &scene {
+        let id = Entity::try_from_bits(*id).map_err(|_| invalid_data(format!("invalid entity id {id}")))?;
+
+        let mut values = Vec::with_capacity(components.len());
+        for (type_path, value) in components {
+            let (registration, reflect) = by_path::<ReflectComponent>(&registry, type_path)?;
+            let value = TypedReflectDeserializer::new(registration, &registry)
+                .deserialize(&mut postcard::Deserializer::from_bytes(value))
+                .map_err(|e| invalid_data(format!("{type_path}: {e}")))?;
+            values.push((type_path, reflect, value));
+        }
+        loaded.push((id, values));
+    }
+
+    let mut map = HashMap::with_capacity(loaded.len());
+    for (id, _) in &loaded {
+        if map.insert(*id, Entity::PLACEHOLDER).is_some() {
+            return Err(invalid_data(format!("entity {id} is saved twice")));
+        }
+    }
+    for spawned in map.values_mut() {
+        *spawned = world.spawn_empty().id();
+    }
+
+    for (_, values) in loaded.iter_mut() {
+        for (type_path, _, value) in values.iter_mut() {
+            if let Err(missing) = map_entities(value.as_mut(), &map) {
+                for &spawned in map.values() {
+                    world.despawn(spawned);
+                }
+                return Err(invalid_data(format!(
+                    "{type_path} references entity {missing}, which is not in the scene"
+                )));
+            }
+        }
+    }
+
+    let mut spawned = Vec::with_capacity(loaded.len());
+    for (id, values) in loaded {
+        let entity = map[&id];
+        let mut entity_mut = world.entity_mut(entity);
+        for (_, reflect, value) in values {
+            reflect.insert(&mut entity_mut, value.as_partial_reflect(), &registry);
+        }
+        spawned.push(entity);
+    }
+
+    Ok(spawned)
+}
+
+/// Reads a scene file from `location` through the world's [`Vfs`] and spawns it, see [`load_scene`].
+pub fn load_scene_file(world: &mut World, path: &str, location: Location) -> io::Result<Vec<Entity>> {
+    let bytes = Vfs::of(world).read(path, location)?;
+    load_scene(world, &bytes)
+}
+
+/// Replaces every [`Entity`] in `value` with the one it maps to.
+/// Returns the first entity that has no mapping.
+fn map_entities(value: &mut dyn PartialReflect, map: &HashMap<Entity, Entity>) -> Result<(), Entity> {
+    if let Some(entity) = value.try_downcast_mut::<Entity>() {
+        *entity = *map.get(entity).ok_or(*entity)?;
+        return Ok(());
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            for i in 0..s.field_len() {
+                map_entities(s.field_at_mut(i).unwrap(), map)?;
+            }
+        }
+        ReflectMut::TupleStruct(s) => {
+            for i in 0..s.field_len() {
+                map_entities(s.field_mut(i).unwrap(), map)?;
+            }
+        }
+        ReflectMut::Tuple(t) => {
+            for i in 0..t.field_len() {
+                map_entities(t.field_mut(i).unwrap(), map)?;
+            }
+        }
+        ReflectMut::List(l) => {
+            for i in 0..l.len() {
+                map_entities(l.get_mut(i).unwrap(), map)?;
+            }
+        }
+        ReflectMut::Array(a) => {
+            for i in 0..a.len() {
+                map_entities(a.get_mut(i).unwrap(), map)?;
+            }
+        }
+        ReflectMut::Enum(e) => {
+            for i in 0..e.field_len() {
+                map_entities(e.field_at_mut(i).unwrap(), map)?;
+            }
+        }
+        // entities used as map or set keys aren't supported
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fs::Location;
+    use crate::scene::{load_scene, load_scene_file, save_scene};
+    use crate::vfs::{MemoryFs, Vfs};
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use bevy_app::App;
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::reflect::ReflectComponent;
+    use bevy_reflect::Reflect;
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Link {
+        to: Entity,
+        via: Vec<Entity>,
+        kind: LinkKind,
+    }
+
+    #[derive(Reflect, Debug, PartialEq)]
+    enum LinkKind {
+        Plain,
+        Back(Entity),
+    }
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Weight(f32);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.register_type::<Link>().register_type::<Weight>();
+        app
+    }
+
+    /// Three entities linking to each other, in the order they are saved.
+    fn spawn_scene(world: &mut World) -> [Entity; 3] {
+        let [a, b, c] = [(); 3].map(|_| world.spawn_empty().id());
+        world.entity_mut(a).insert((
+            Link { to: b, via: vec![b, c], kind: LinkKind::Back(c) },
+            Weight(2.0),
+        ));
+        world.entity_mut(b).insert(Link { to: a, via: vec![], kind: LinkKind::Plain });
+        world.entity_mut(c).insert(Weight(0.5));
+        [c, a, b]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut saved = app();
+        let entities = spawn_scene(saved.world_mut());
+        let bytes = save_scene(saved.world(), entities).unwrap();
+
+        let mut loaded = app();
+        let world = loaded.world_mut();
+        // so the new ids differ from the saved ones
+        world.spawn_empty();
+        let [c, a, b] = <[Entity; 3]>::try_from(load_scene(world, &bytes).unwrap()).unwrap();
+        assert_ne!([c, a, b], entities);
+
+        assert_eq!(
+            world.get::<Link>(a),
+            Some(&Link { to: b, via: vec![b, c], kind: LinkKind::Back(c) })
+        );
+        assert_eq!(world.get::<Weight>(a), Some(&Weight(2.0)));
+        assert_eq!(world.get::<Link>(b), Some(&Link { to: a, via: vec![], kind: LinkKind::Plain }));
+        assert_eq!(world.get::<Weight>(c), Some(&Weight(0.5)));
+        assert_eq!(world.get::<Link>(c), None);
+    }
+
+    #[test]
+    fn test_missing_reference() {
+        let mut saved = app();
+        let [_, a, _] = spawn_scene(saved.world_mut());
+        let bytes = save_scene(saved.world(), [a]).unwrap();
+
+        let mut loaded = app();
+        let world = loaded.world_mut();
+        let before = world.entities().len();
+        assert!(load_scene(world, &bytes).is_err());
+        assert_eq!(world.entities().len(), before);
+        assert_eq!(world.query::<&Link>().iter(world).count(), 0);
+    }
+
+    #[test]
+    fn test_unknown_component() {
+        let mut saved = app();
+        let entities = spawn_scene(saved.world_mut());
+        let bytes = save_scene(saved.world(), entities).unwrap();
+
+        let mut loaded = App::new();
+        loaded.register_type::<Weight>();
+        let world = loaded.world_mut();
+        assert!(load_scene(world, &bytes).is_err());
+        assert!(load_scene(world, b"not a scene").is_err());
+        assert_eq!(world.query::<&Weight>().iter(world).count(), 0);
+    }
+
+    #[test]
+    fn test_load_from_bundle() {
+        let mut saved = app();
+        let entities = spawn_scene(saved.world_mut());
+        let bytes = save_scene(saved.world(), entities).unwrap();
+
+        let memory = MemoryFs::default();
+        memory.insert_bundle("tracks/loop.scn", bytes);
+        let mut loaded = app();
+        loaded.insert_resource(Vfs::new(memory));
+        let world = loaded.world_mut();
+
+        let spawned = load_scene_file(world, "tracks/loop.scn", Location::Bundle).unwrap();
+        assert_eq!(spawned.len(), 3);
+        assert!(load_scene_file(world, "tracks/loop.scn", Location::Data).is_err());
+    }
+}
@@ -32,6 +32,14 @@ impl Time {
     pub fn elapsed_secs(&self) -> f32 {
         self.elapsed().as_secs_f32()
     }
+
+    /// Time since startup, including the time spent in the current frame so far.
+    ///
+    /// Unlike the Playdate elapsed-time counter this is not reset every frame,
+    /// so it can be used to measure sections of a frame.
+    pub fn now(&self) -> Duration {
+        self.now + self.pd_time.elapsed_time()
+    }
 }
 
 impl Default for Time {
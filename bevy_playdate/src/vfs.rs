@@ -0,0 +1,530 @@
+//! Swappable filesystem backend, so file-based code can run off-device.
+//!
+//! [`Vfs`] defaults to the Playdate's filesystem. Insert one backed by a [`MemoryFs`]
+//! to run the same code under `cargo test`:
+//!
+//! ```ignore
+//! let memory = MemoryFs::default();
+//! memory.load_host_dir("assets")?; // needs the `std` feature
+//! app.insert_resource(Vfs::new(memory));
+//! ```
+
+use crate::file::FileHandle;
+use crate::fs::{self, DirEntry, FileTime, Location, Metadata};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_ecs::prelude::Resource;
+use bevy_ecs::world::World;
+use core::cell::RefCell;
+use no_std_io2::io::{self, Read, Seek, SeekFrom, Write};
+use playdate::sys::ffi::FileOptions;
+
+/// How to open a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OpenMode {
+    Read(Location),
+    /// Creates or truncates the file in the data folder.
+    Write,
+    /// Creates the file in the data folder, or writes to its end.
+    Append,
+}
+
+/// A filesystem backend. Writes always go to the data folder.
+pub trait FileSystem {
+    fn open(&self, path: &str, mode: OpenMode) -> io::Result<VfsFile>;
+    fn metadata(&self, path: &str) -> io::Result<Metadata>;
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>>;
+    fn create_dir(&self, path: &str) -> io::Result<()>;
+    fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+    fn remove(&self, path: &str) -> io::Result<()>;
+
+    fn exists(&self, path: &str) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn read(&self, path: &str, location: Location) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.open(path, OpenMode::Read(location))?.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write(&self, path: &str, contents: &[u8]) -> io::Result<()> {
+        let mut file = self.open(path, OpenMode::Write)?;
+        file.write_all(contents)?;
+        file.flush()
+    }
+}
+
+/// Shared handle to the [`FileSystem`] in use.
+#[derive(Resource, Clone)]
+pub struct Vfs(Rc<dyn FileSystem>);
+
+// SAFETY: The Playdate is single-threaded.
+unsafe impl Send for Vfs {}
+unsafe impl Sync for Vfs {}
+
+impl Default for Vfs {
+    fn default() -> Self {
+        Self::new(PlaydateFs)
+    }
+}
+
+impl Vfs {
+    pub fn new(fs: impl FileSystem + 'static) -> Self {
+        Self(Rc::new(fs))
+    }
+
+    /// The world's [`Vfs`], or the Playdate filesystem if there is none.
+    pub fn of(world: &World) -> Self {
+        world.get_resource::<Vfs>().cloned().unwrap_or_default()
+    }
+}
+
+impl core::ops::Deref for Vfs {
+    type Target = dyn FileSystem;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+/// A file opened through a [`FileSystem`].
+pub struct VfsFile(Box<dyn ReadWriteSeek>);
+
+trait ReadWriteSeek: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> ReadWriteSeek for T {}
+
+impl VfsFile {
+    pub fn new(file: impl Read + Write + Seek + 'static) -> Self {
+        Self(Box::new(file))
+    }
+}
+
+impl Read for VfsFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for VfsFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Seek for VfsFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+/// The Playdate's own filesystem, see [`crate::fs`].
+pub struct PlaydateFs;
+
+impl FileSystem for PlaydateFs {
+    fn open(&self, path: &str, mode: OpenMode) -> io::Result<VfsFile> {
+        let options = match mode {
+            OpenMode::Read(location) => location.read_options(),
+            OpenMode::Write => FileOptions::kFileWrite,
+            OpenMode::Append => FileOptions::kFileAppend,
+        };
+        FileHandle::open(path, options).map(VfsFile::new)
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        fs::metadata(path)
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        fs::read_dir(path, false).map(Iterator::collect)
+    }
+
+    fn create_dir(&self, path: &str) -> io::Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        fs::remove(path)
+    }
+}
+
+type MemoryData = Rc<RefCell<Vec<u8>>>;
+
+/// A filesystem kept in memory, with a read-only bundle and a writable data folder like the device.
+///
+/// Directories are implied by the paths of the files in them.
+#[derive(Default)]
+pub struct MemoryFs {
+    bundle: RefCell<BTreeMap<String, MemoryData>>,
+    data: RefCell<BTreeMap<String, MemoryData>>,
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./").trim_matches('/')
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, alloc::format!("{path} not found"))
+}
+
+impl MemoryFs {
+    /// Adds a file to the bundle.
+    pub fn insert_bundle(&self, path: &str, contents: impl Into<Vec<u8>>) {
+        self.bundle.borrow_mut().insert(
+            normalize(path).to_string(),
+            Rc::new(RefCell::new(contents.into())),
+        );
+    }
+
+    /// Adds a file to the data folder.
+    pub fn insert_data(&self, path: &str, contents: impl Into<Vec<u8>>) {
+        self.data.borrow_mut().insert(
+            normalize(path).to_string(),
+            Rc::new(RefCell::new(contents.into())),
+        );
+    }
+
+    /// Copies every file under `dir` on the host into the bundle.
+    #[cfg(feature = "std")]
+    pub fn load_host_dir(&self, dir: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        fn visit(fs: &MemoryFs, root: &std::path::Path, dir: &std::path::Path) -> std::io::Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    visit(fs, root, &path)?;
+                } else {
+                    let relative = path.strip_prefix(root).unwrap();
+                    let key = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    fs.insert_bundle(&key, std::fs::read(&path)?);
+                }
+            }
+            Ok(())
+        }
+
+        let dir = dir.as_ref();
+        visit(self, dir, dir)
+    }
+
+    fn find(&self, path: &str, location: Location) -> Option<MemoryData> {
+        let data = || self.data.borrow().get(path).cloned();
+        let bundle = || self.bundle.borrow().get(path).cloned();
+        match location {
+            Location::Bundle => bundle(),
+            Location::Data => data(),
+            Location::Any => data().or_else(bundle),
+        }
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        let prefix = alloc::format!("{path}/");
+        path.is_empty()
+            || [&self.data, &self.bundle]
+                .iter()
+                .any(|files| files.borrow().keys().any(|k| k.starts_with(&prefix)))
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn open(&self, path: &str, mode: OpenMode) -> io::Result<VfsFile> {
+        let path = normalize(path);
+        let (data, pos, writable) = match mode {
+            OpenMode::Read(location) => {
+                let data = self.find(path, location).ok_or_else(|| not_found(path))?;
+                (data, 0, false)
+            }
+            OpenMode::Write => {
+                let data = MemoryData::default();
+                self.data.borrow_mut().insert(path.to_string(), data.clone());
+                (data, 0, true)
+            }
+            OpenMode::Append => {
+                let data = self
+                    .data
+                    .borrow_mut()
+                    .entry(path.to_string())
+                    .or_default()
+                    .clone();
+                let len = data.borrow().len();
+                (data, len, true)
+            }
+        };
+        Ok(VfsFile::new(MemoryFile { data, pos, writable }))
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let path = normalize(path);
+        if let Some(data) = self.find(path, Location::Any) {
+            return Ok(Metadata {
+                is_dir: false,
+                size: data.borrow().len() as u32,
+                modified: FileTime::default(),
+            });
+        }
+        if self.is_dir(path) {
+            return Ok(Metadata {
+                is_dir: true,
+                size: 0,
+                modified: FileTime::default(),
+            });
+        }
+        Err(not_found(path))
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let path = normalize(path);
+        if !self.is_dir(path) {
+            return Err(not_found(path));
+        }
+        let prefix = if path.is_empty() {
+            String::new()
+        } else {
+            alloc::format!("{path}/")
+        };
+
+        let mut entries: BTreeMap<String, bool> = BTreeMap::new();
+        for files in [&self.data, &self.bundle] {
+            for key in files.borrow().keys() {
+                let Some(rest) = key.strip_prefix(&prefix) else {
+                    continue;
+                };
+                match rest.split_once('/') {
+                    Some((dir, _)) => entries.insert(dir.to_string(), true),
+                    None => entries.insert(rest.to_string(), false),
+                };
+            }
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, is_dir)| DirEntry { name, is_dir })
+            .collect())
+    }
+
+    fn create_dir(&self, _path: &str) -> io::Result<()> {
+        // directories only exist through the files in them
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let (from, to) = (normalize(from), normalize(to));
+        let mut data = self.data.borrow_mut();
+        let file = data.remove(from).ok_or_else(|| not_found(from))?;
+        data.insert(to.to_string(), file);
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> io::Result<()> {
+        let path = normalize(path);
+        self.data
+            .borrow_mut()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+/// An open file of a [`MemoryFs`].
+struct MemoryFile {
+    data: MemoryData,
+    pos: usize,
+    writable: bool,
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.data.borrow();
+        let remaining = data.get(self.pos..).unwrap_or_default();
+        let n = remaining.len().min(buf.len());
+        buf[..n].copy_from_slice(&remaining[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.writable {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "file is read-only"));
+        }
+        let mut data = self.data.borrow_mut();
+        let end = self.pos + buf.len();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[self.pos..end].copy_from_slice(buf);
+        self.pos = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.data.borrow().len() as i64;
+        let pos = match pos {
+            SeekFrom::Start(n) => n as i64,
+            SeekFrom::End(n) => len + n,
+            SeekFrom::Current(n) => self.pos as i64 + n,
+        };
+        if pos < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "seek before start of file"));
+        }
+        self.pos = pos as usize;
+        Ok(pos as u64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fs::{DirEntry, Location};
+    use crate::save::{load_game, save_game, Persistent, SaveAppExt, SavePlugin};
+    use crate::vfs::{FileSystem, MemoryFs, OpenMode, Vfs};
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use bevy_app::App;
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::reflect::{ReflectComponent, ReflectResource};
+    use bevy_reflect::Reflect;
+    use no_std_io2::io::{ErrorKind, Write};
+
+    fn entry(name: &str, is_dir: bool) -> DirEntry {
+        DirEntry {
+            name: name.to_string(),
+            is_dir,
+        }
+    }
+
+    #[test]
+    fn test_memory_read_write() {
+        let fs = MemoryFs::default();
+        fs.insert_bundle("levels/1.txt", "bundled");
+
+        assert_eq!(fs.read("levels/1.txt", Location::Bundle).unwrap(), b"bundled");
+        assert_eq!(fs.read("./levels/1.txt", Location::Any).unwrap(), b"bundled");
+        assert_eq!(
+            fs.read("levels/1.txt", Location::Data).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        // writes go to the data folder, which is read first
+        fs.write("levels/1.txt", b"edited").unwrap();
+        assert_eq!(fs.read("levels/1.txt", Location::Any).unwrap(), b"edited");
+        assert_eq!(fs.read("levels/1.txt", Location::Bundle).unwrap(), b"bundled");
+
+        let mut file = fs.open("levels/1.txt", OpenMode::Append).unwrap();
+        file.write_all(b"!").unwrap();
+        assert_eq!(fs.read("levels/1.txt", Location::Data).unwrap(), b"edited!");
+
+        let mut file = fs.open("levels/1.txt", OpenMode::Read(Location::Bundle)).unwrap();
+        assert_eq!(file.write(b"x").unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+        assert_eq!(fs.metadata("levels/1.txt").unwrap().size, 7);
+        assert!(fs.metadata("levels").unwrap().is_dir);
+        assert!(!fs.exists("levels/2.txt"));
+    }
+
+    #[test]
+    fn test_memory_rename() {
+        let fs = MemoryFs::default();
+        fs.insert_data("save.json.tmp", "new");
+        fs.insert_data("save.json", "old");
+
+        fs.rename("save.json.tmp", "save.json").unwrap();
+        assert_eq!(fs.read("save.json", Location::Data).unwrap(), b"new");
+        assert!(!fs.exists("save.json.tmp"));
+
+        assert_eq!(fs.rename("missing", "save.json").unwrap_err().kind(), ErrorKind::NotFound);
+
+        fs.remove("save.json").unwrap();
+        assert!(!fs.exists("save.json"));
+        assert_eq!(fs.remove("save.json").unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_memory_read_dir() {
+        let fs = MemoryFs::default();
+        fs.insert_bundle("images/player.png", "");
+        fs.insert_bundle("images/tiles/grass.png", "");
+        fs.insert_data("images/screenshot.png", "");
+        fs.insert_data("save.json", "");
+
+        assert_eq!(
+            fs.read_dir("images").unwrap(),
+            vec![
+                entry("player.png", false),
+                entry("screenshot.png", false),
+                entry("tiles", true),
+            ]
+        );
+        assert_eq!(
+            fs.read_dir("").unwrap(),
+            vec![entry("images", true), entry("save.json", false)]
+        );
+        assert_eq!(fs.read_dir("sounds").unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[derive(Resource, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Resource)]
+    struct Settings {
+        volume: f32,
+    }
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Score(u32);
+
+    #[test]
+    fn test_save_round_trip() {
+        let mut app = App::new();
+        app.add_plugins(SavePlugin::new("save.json", 1))
+            .insert_resource(Vfs::new(MemoryFs::default()))
+            .save_resource::<Settings>()
+            .save_component::<Score>();
+
+        let world = app.world_mut();
+        world.insert_resource(Settings { volume: 0.5 });
+        let player = world.spawn((Persistent::new("player"), Score(3))).id();
+
+        save_game(world).unwrap();
+        let vfs = Vfs::of(world);
+        assert!(vfs.exists("save.json"));
+        assert!(!vfs.exists("save.json.tmp"));
+
+        world.resource_mut::<Settings>().volume = 0.0;
+        world.despawn(player);
+
+        assert!(load_game(world).unwrap());
+        assert_eq!(world.resource::<Settings>().volume, 0.5);
+        let players: Vec<_> = world.query::<(&Persistent, &Score)>().iter(world).collect();
+        assert_eq!(players, vec![(&Persistent::new("player"), &Score(3))]);
+    }
+
+    #[test]
+    fn test_load_without_save() {
+        let mut app = App::new();
+        app.add_plugins(SavePlugin::new("save.json", 1))
+            .insert_resource(Vfs::new(MemoryFs::default()));
+
+        assert!(!load_game(app.world_mut()).unwrap());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::arc::ArcSegment;
+use crate::line::LineSegment;
+use crate::roots::{quadratic, SolutionIter};
+use crate::traits::CurveType;
+use core::f32::consts::TAU;
+use glam::Vec2;
+use num_traits::{Euclid, Float};
+
+/// Where a straight path crosses a curve.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Hit {
+    /// How far along the path, from 0 at its start to 1 at its end.
+    pub s: f32,
+    /// `t`-value on the curve.
+    pub t: f32,
+}
+
+/// The first point where the path from `a` to `b` crosses `curve`.
+pub fn intersect(curve: &CurveType, a: Vec2, b: Vec2) -> Option<Hit> {
+    match curve {
+        CurveType::Line(line) => intersect_line(line, a, b),
+        CurveType::Arc(arc) => intersect_arc(arc, a, b),
+    }
+}
+
+pub fn intersect_line(line: &LineSegment, a: Vec2, b: Vec2) -> Option<Hit> {
+    let path = b - a;
+    let edge = line.end - line.start;
+    let denom = path.perp_dot(edge);
+    if denom == 0.0 {
+        // parallel
+        return None;
+    }
+
+    let offset = line.start - a;
+    let s = offset.perp_dot(edge) / denom;
+    let t = offset.perp_dot(path) / denom;
+    ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)).then_some(Hit { s, t })
+}
+
+pub fn intersect_arc(arc: &ArcSegment, a: Vec2, b: Vec2) -> Option<Hit> {
+    let path = b - a;
+    let offset = a - arc.center;
+    // |a + s * path - center|^2 = radius^2
+    let roots = quadratic(
+        path.length_squared(),
+        2.0 * path.dot(offset),
+        offset.length_squared() - arc.radius * arc.radius,
+    );
+
+    SolutionIter::from(roots)
+        .filter(|s| (0.0..=1.0).contains(s))
+        .filter_map(|s| {
+            let p = offset + path * s;
+            // `ArcSegment::eval_angle` flips y
+            let angle = f32::atan2(-p.y, p.x);
+            let span = arc.end - arc.start;
+            let from_start = if span >= 0.0 {
+                (angle - arc.start).rem_euclid(&TAU)
+            } else {
+                -(arc.start - angle).rem_euclid(&TAU)
+            };
+            let t = if span == 0.0 { 0.0 } else { from_start / span };
+            (0.0..=1.0).contains(&t).then_some(Hit { s, t })
+        })
+        .min_by(|x, y| x.s.total_cmp(&y.s))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::arc::ArcSegment;
+    use crate::intersect::{intersect_arc, intersect_line};
+    use crate::line::LineSegment;
+    use crate::traits::CurveSegment;
+    use core::f32::consts::PI;
+    use glam::Vec2;
+
+    #[test]
+    fn test_intersect_line() {
+        let line = LineSegment {
+            start: Vec2::new(0.0, 0.0),
+            end: Vec2::new(10.0, 0.0),
+        };
+
+        let hit = intersect_line(&line, Vec2::new(2.0, -5.0), Vec2::new(2.0, 5.0)).unwrap();
+        assert!((hit.s - 0.5).abs() < 1e-6);
+        assert!((hit.t - 0.2).abs() < 1e-6);
+
+        assert_eq!(intersect_line(&line, Vec2::new(12.0, -5.0), Vec2::new(12.0, 5.0)), None);
+        assert_eq!(intersect_line(&line, Vec2::new(0.0, 1.0), Vec2::new(10.0, 1.0)), None);
+    }
+
+    #[test]
+    fn test_intersect_arc() {
+        // top half of a circle, as y is flipped
+        let arc = ArcSegment {
+            center: Vec2::ZERO,
+            start: 0.0,
+            end: PI,
+            radius: 10.0,
+        };
+
+        // falling onto the top from above
+        let hit = intersect_arc(&arc, Vec2::new(0.0, -20.0), Vec2::new(0.0, 0.0)).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-5);
+        assert!((arc.position(hit.t) - Vec2::new(0.0, -10.0)).length() < 1e-3);
+
+        // the bottom half isn't part of the arc
+        assert_eq!(intersect_arc(&arc, Vec2::new(0.0, 20.0), Vec2::new(0.0, 0.0)), None);
+
+        // clockwise arcs go the other way
+        let reversed = ArcSegment {
+            start: PI,
+            end: 0.0,
+            ..arc
+        };
+        let hit = intersect_arc(&reversed, Vec2::new(-5.0, -20.0), Vec2::new(-5.0, 0.0)).unwrap();
+        assert!((reversed.position(hit.t) - Vec2::new(-5.0, -8.660254)).length() < 1e-3);
+    }
+}
@@ -2,6 +2,7 @@
 
 extern crate alloc;
 pub mod arc;
+pub mod intersect;
 pub mod line;
 pub mod roots;
 pub mod traits;
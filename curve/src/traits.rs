@@ -4,7 +4,7 @@ use bevy_math::Dir2;
 use bevy_reflect::Reflect;
 use derive_more::From;
 use glam::Vec2;
-use ode_solvers::{SVector, System};
+use ode_solvers::{Rk4, SVector, System};
 
 pub trait CurveSegment: Send + Sync + 'static {
     /// Length of this curve segment.
@@ -24,16 +24,82 @@ pub trait CurveSegment: Send + Sync + 'static {
     fn bounds(&self) -> (Vec2, Vec2);
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum CurveEnd {
     Start,
     End,
 }
 
+impl CurveEnd {
+    /// The `t`-value of this end.
+    pub fn t(self) -> f32 {
+        match self {
+            CurveEnd::Start => 0.0,
+            CurveEnd::End => 1.0,
+        }
+    }
+}
+
+/// Result of [`CurveSegmentSystem::integrate`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Motion {
+    /// Time taken, less than the duration if an end was reached.
+    pub time: f32,
+    pub t: f32,
+    pub v: f32,
+    /// The end of the curve that was reached, if any. `t` is then exactly at that end.
+    pub end: Option<CurveEnd>,
+}
+
 pub struct CurveSegmentSystem<'a, Curve: CurveSegment> {
     pub curve: &'a Curve,
     pub gravity: Vec2,
 }
 
+impl<Curve: CurveSegment> CurveSegmentSystem<'_, Curve> {
+    /// Moves a dot at `t` with velocity `v` (along the curve, in units per second) for `duration`
+    /// seconds, or until it reaches an end, with steps of at most `max_step` seconds.
+    ///
+    /// Unlike assuming the pull of gravity stays the same over a step, this follows the
+    /// tangent as it turns, so energy is conserved around arcs.
+    pub fn integrate(self, t: f32, v: f32, duration: f32, max_step: f32) -> Motion {
+        if duration <= 0.0 {
+            return Motion { time: 0.0, t, v, end: None };
+        }
+
+        // equal steps that land exactly on `duration`
+        let steps = (duration / max_step).ceil().max(1.0);
+        let mut solver = Rk4::new(self, 0.0, SVector::<f32, 2>::new(t, v), duration, duration / steps);
+        if solver.integrate().is_err() {
+            return Motion { time: 0.0, t, v, end: None };
+        }
+
+        let (xs, ys) = (solver.x_out(), solver.y_out());
+        let (x, y) = (xs[xs.len() - 1], ys[ys.len() - 1]);
+        let end = if y[0] > 1.0 {
+            CurveEnd::End
+        } else if y[0] < 0.0 {
+            CurveEnd::Start
+        } else {
+            return Motion { time: x, t: y[0], v: y[1], end: None };
+        };
+
+        // `solout` stopped right after crossing the end, find where within the last step it was
+        let (x0, y0) = if xs.len() > 1 {
+            (xs[xs.len() - 2], ys[ys.len() - 2])
+        } else {
+            (0.0, SVector::<f32, 2>::new(t, v))
+        };
+        let f = ((end.t() - y0[0]) / (y[0] - y0[0])).clamp(0.0, 1.0);
+        Motion {
+            time: x0 + (x - x0) * f,
+            t: end.t(),
+            v: y0[1] + (y[1] - y0[1]) * f,
+            end: Some(end),
+        }
+    }
+}
+
 impl<Curve: CurveSegment> System<f32, SVector<f32, 2>> for CurveSegmentSystem<'_, Curve> {
     fn system(&self, _x: f32, y: &SVector<f32, 2>, dy: &mut SVector<f32, 2>) {
         let [[t, v]] = y.data.0;
@@ -90,3 +156,55 @@ impl CurveSegment for CurveType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::arc::ArcSegment;
+    use crate::traits::{CurveEnd, CurveSegment, CurveSegmentSystem};
+    use core::f32::consts::PI;
+    use glam::Vec2;
+
+    fn energy(curve: &ArcSegment, gravity: Vec2, t: f32, v: f32) -> f32 {
+        0.5 * v * v - gravity.dot(curve.position(t))
+    }
+
+    #[test]
+    fn test_integrate_conserves_energy_on_arc() {
+        let circle = ArcSegment {
+            center: Vec2::ZERO,
+            start: 0.0,
+            end: 2.0 * PI,
+            radius: 50.0,
+        };
+        let gravity = Vec2::new(0.0, 100.0);
+        let (t, v) = (0.1, 150.0);
+
+        let motion = CurveSegmentSystem { curve: &circle, gravity }.integrate(t, v, 0.5, 0.005);
+
+        assert_eq!(motion.end, None);
+        assert!((motion.time - 0.5).abs() < 1e-4);
+        let (before, after) = (energy(&circle, gravity, t, v), energy(&circle, gravity, motion.t, motion.v));
+        assert!((before - after).abs() / before.abs() < 1e-3, "{before} != {after}");
+    }
+
+    #[test]
+    fn test_integrate_stops_at_end() {
+        let arc = ArcSegment {
+            center: Vec2::ZERO,
+            start: 0.0,
+            end: PI / 2.0,
+            radius: 50.0,
+        };
+        let motion = CurveSegmentSystem {
+            curve: &arc,
+            gravity: Vec2::ZERO,
+        }
+        .integrate(0.5, 100.0, 10.0, 0.01);
+
+        assert_eq!(motion.end, Some(CurveEnd::End));
+        assert_eq!(motion.t, 1.0);
+        // half of a quarter circle of radius 50 at 100 per second
+        assert!((motion.time - arc.length() / 2.0 / 100.0).abs() < 1e-3);
+        assert!((motion.v - 100.0).abs() < 1e-3);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::curve::{Joint, JointConnection, Segment, SegmentConnection};
+use crate::curve::{leaving, Joint, JointConnection, Segment, SegmentConnection};
 use alloc::vec::Vec;
 use bevy_ecs::component::Component;
 use bevy_ecs::entity::Entity;
@@ -11,13 +11,15 @@ use curve::arc::ArcSegment;
 use curve::line::LineSegment;
 use curve::traits::{CurveSegment, CurveType};
 use glam::{FloatExt, Vec2};
-use num_traits::Euclid;
+use num_traits::{Euclid, Float};
 use pd::graphics::api::Api;
 use pd::graphics::bitmap::LCDColorConst;
 use smallvec::smallvec;
 
 pub struct CurveBuilder {
     segments: Vec<SplineSegment>,
+    /// Curves of the branches, see [`CurveBuilder::branch`].
+    branches: Vec<CurveType>,
     cur_pos: Vec2,
     cur_dir: Vec2,
     total_length: f32,
@@ -27,12 +29,79 @@ impl CurveBuilder {
     pub fn new(pos: Vec2, dir: Vec2) -> Self {
         CurveBuilder {
             segments: Vec::new(),
+            branches: Vec::new(),
             cur_pos: pos,
             cur_dir: dir,
             total_length: 0.0,
         }
     }
 
+    /// Where the next segment starts.
+    pub fn position(&self) -> Vec2 {
+        self.cur_pos
+    }
+
+    /// Direction the next segment starts in.
+    pub fn direction(&self) -> Vec2 {
+        self.cur_dir
+    }
+
+    /// Starts a curve at a saved [`Cursor`].
+    pub fn at(cursor: Cursor) -> Self {
+        Self::new(cursor.pos, cursor.dir)
+    }
+
+    /// Where the next segment starts and which way it goes, to branch off from later.
+    pub fn cursor(&self) -> Cursor {
+        Cursor {
+            pos: self.cur_pos,
+            dir: self.cur_dir,
+        }
+    }
+
+    /// The curves built so far, including branches, without spawning them.
+    pub fn into_curves(self) -> Vec<CurveType> {
+        self.segments.into_iter().map(|s| s.segment).chain(self.branches).collect()
+    }
+
+    /// Builds a branch forking off here with `branch`, then carries on from here.
+    ///
+    /// The fork becomes a joint where the branch and the next segment share a connection.
+    pub fn branch(self, branch: impl FnOnce(CurveBuilder) -> CurveBuilder) -> Self {
+        let cursor = self.cursor();
+        self.branch_from(cursor, branch)
+    }
+
+    /// Builds a branch starting at `cursor` with `branch`, e.g. from a cursor saved earlier
+    /// or one [reversed](Cursor::reversed) to branch off backwards.
+    pub fn branch_from(mut self, cursor: Cursor, branch: impl FnOnce(CurveBuilder) -> CurveBuilder) -> Self {
+        self.branches.extend(branch(CurveBuilder::at(cursor)).into_curves());
+        self
+    }
+
+    /// Ends the curve at `cursor`, with a straight line if it isn't already there,
+    /// merging it into the joint at the cursor when built.
+    ///
+    /// Tracks only share a connection if they also meet at the same angle.
+    pub fn join(self, cursor: Cursor) -> Self {
+        if self.cur_pos.distance(cursor.pos) < 1e-3 {
+            self
+        } else {
+            self.push(builders::line_to(cursor.pos))
+        }
+    }
+
+    /// A [`GraphBuilder`] of the curves, including branches.
+    ///
+    /// `join_ends` connects the end back to the start, even if they don't meet.
+    pub fn into_graph(self, line_width: i32, join_ends: bool) -> GraphBuilder {
+        let mut graph = GraphBuilder::new();
+        if join_ends && !self.segments.is_empty() {
+            graph = graph.connect((0, 0.0), (self.segments.len() - 1, 1.0));
+        }
+        graph.extend(self.into_curves(), line_width)
+    }
+
     pub fn push(mut self, builder: impl SectionBuilder) -> Self {
         let segment = builder.add_segment(&mut self.cur_pos, &mut self.cur_dir);
 
@@ -99,15 +168,22 @@ impl CurveBuilder {
         self
     }
 
+    /// Spawns the curve under a new [`CurveParent`], returning its segments and joints.
+    ///
+    /// `join_ends` connects the end back to the start. With branches, the graph is built by
+    /// [`GraphBuilder`] instead, which also joins any other ends that meet.
     pub fn build(
         self,
         commands: &mut Commands,
         line_width: i32,
         join_ends: bool,
     ) -> (Vec<Entity>, Vec<Entity>) {
-        if self.segments.is_empty() {
+        if self.segments.is_empty() && self.branches.is_empty() {
             return Default::default();
         }
+        if !self.branches.is_empty() {
+            return self.into_graph(line_width, join_ends).build(commands);
+        }
         // TODO when bevy_hierarchy ports, make children
         let parent = commands.spawn((Transform::default(), CurveParent)).id();
 
@@ -202,6 +278,222 @@ impl CurveBuilder {
 #[derive(Copy, Clone, Debug, Default, Component)]
 pub struct CurveParent;
 
+/// A saved position and direction of a [`CurveBuilder`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cursor {
+    pub pos: Vec2,
+    pub dir: Vec2,
+}
+
+impl Cursor {
+    /// The same position, facing the other way.
+    pub fn reversed(self) -> Self {
+        Cursor {
+            pos: self.pos,
+            dir: -self.dir,
+        }
+    }
+}
+
+/// Connects loose curves into a graph, creating a [`Joint`] wherever their ends meet.
+///
+/// Ends within `distance` of each other share a joint. At each joint, ends leaving in the same
+/// direction (within `angle` radians) share a [`JointConnection`], sorted by curvature from the
+/// sharpest right turn to the sharpest left turn.
+pub struct GraphBuilder {
+    curves: Vec<(CurveType, i32)>,
+    /// Ends put in the same joint whether or not they meet, see [`GraphBuilder::connect`].
+    connected: Vec<((usize, f32), (usize, f32))>,
+    distance: f32,
+    angle: f32,
+}
+
+impl Default for GraphBuilder {
+    fn default() -> Self {
+        GraphBuilder {
+            curves: Vec::new(),
+            connected: Vec::new(),
+            distance: 1.0,
+            angle: 5f32.to_radians(),
+        }
+    }
+}
+
+/// The joints of a [`GraphBuilder`], with curves and joints referred to by index.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Graph {
+    /// Start and end joint of each curve.
+    pub(crate) ends: Vec<(usize, usize)>,
+    /// The connections of each joint, as the (curve, `t`) ends in each.
+    pub(crate) joints: Vec<Vec<Vec<(usize, f32)>>>,
+}
+
+/// An end of a curve at a joint.
+struct CurveEnd {
+    curve: usize,
+    t: f32,
+    /// Direction leaving the joint.
+    dir: Vec2,
+    /// Curvature leaving the joint, positive to the left.
+    curvature: f32,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tolerance(mut self, distance: f32, angle: f32) -> Self {
+        self.distance = distance;
+        self.angle = angle;
+        self
+    }
+
+    /// Puts two curve ends in the same joint, even if they don't meet.
+    ///
+    /// Ends are given as the index of the curve, in the order they were added, and `t`.
+    pub fn connect(mut self, a: (usize, f32), b: (usize, f32)) -> Self {
+        self.connected.push((a, b));
+        self
+    }
+
+    pub fn push(mut self, curve: impl Into<CurveType>, line_width: i32) -> Self {
+        self.curves.push((curve.into(), line_width));
+        self
+    }
+
+    pub fn extend(mut self, curves: impl IntoIterator<Item = CurveType>, line_width: i32) -> Self {
+        self.curves.extend(curves.into_iter().map(|curve| (curve, line_width)));
+        self
+    }
+
+    /// Spawns the graph under a new [`CurveParent`].
+    pub fn build(self, commands: &mut Commands) -> (Vec<Entity>, Vec<Entity>) {
+        let parent = commands.spawn((Transform::default(), CurveParent)).id();
+        self.build_in(commands, parent)
+    }
+
+    /// Finds the joints where the curves' ends meet, without spawning anything.
+    pub(crate) fn graph(&self) -> Graph {
+        // joint position and the curve ends at it
+        let mut joints: Vec<(Vec2, Vec<CurveEnd>)> = Vec::new();
+        let mut ends = Vec::with_capacity(self.curves.len());
+        for (i, (curve, _)) in self.curves.iter().enumerate() {
+            let mut add_end = |t: f32| {
+                let position = curve.position(t);
+                let (dir, curvature) = leaving(curve, t);
+                let end = CurveEnd { curve: i, t, dir, curvature };
+                match joints.iter().position(|(p, _)| p.distance(position) <= self.distance) {
+                    Some(joint) => {
+                        joints[joint].1.push(end);
+                        joint
+                    }
+                    None => {
+                        joints.push((position, alloc::vec![end]));
+                        joints.len() - 1
+                    }
+                }
+            };
+            ends.push((add_end(0.0), add_end(1.0)));
+        }
+
+        for &(a, b) in &self.connected {
+            let joint_of = |ends: &[(usize, usize)], (curve, t): (usize, f32)| {
+                if t == 0.0 { ends[curve].0 } else { ends[curve].1 }
+            };
+            let (keep, merge) = (joint_of(&ends, a), joint_of(&ends, b));
+            if keep == merge {
+                continue;
+            }
+
+            let (_, merged) = joints.remove(merge);
+            let keep = if keep > merge { keep - 1 } else { keep };
+            joints[keep].1.extend(merged);
+            for (start, end) in &mut ends {
+                for joint in [start, end] {
+                    if *joint == merge {
+                        *joint = keep;
+                    } else if *joint > merge {
+                        *joint -= 1;
+                    }
+                }
+            }
+        }
+
+        let min_dot = self.angle.cos();
+        let joints = joints
+            .into_iter()
+            .map(|(_, ends)| {
+                let mut groups: Vec<Vec<CurveEnd>> = Vec::new();
+                for end in ends {
+                    match groups.iter_mut().find(|group| group[0].dir.dot(end.dir) >= min_dot) {
+                        Some(group) => group.push(end),
+                        None => groups.push(alloc::vec![end]),
+                    }
+                }
+
+                groups
+                    .into_iter()
+                    .map(|mut group| {
+                        group.sort_by(|a, b| a.curvature.total_cmp(&b.curvature));
+                        group.iter().map(|end| (end.curve, end.t)).collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Graph { ends, joints }
+    }
+
+    /// Spawns the graph as children of `parent`, returning the segments (in the order they were
+    /// added) and the joints.
+    pub fn build_in(self, commands: &mut Commands, parent: Entity) -> (Vec<Entity>, Vec<Entity>) {
+        let Graph { ends, joints } = self.graph();
+
+        let seg_entities: Vec<Entity> = self
+            .curves
+            .iter()
+            .map(|_| commands.spawn(Name::new("Segment")).id())
+            .collect();
+        let joint_entities: Vec<Entity> = joints
+            .iter()
+            .map(|_| commands.spawn(Name::new("Joint")).id())
+            .collect();
+
+        commands.entity(parent).add_children(&seg_entities).add_children(&joint_entities);
+
+        for (connections, &entity) in joints.into_iter().zip(&joint_entities) {
+            let connections = connections
+                .into_iter()
+                .map(|group| JointConnection {
+                    segments: group
+                        .into_iter()
+                        .map(|(curve, t)| SegmentConnection {
+                            id: seg_entities[curve],
+                            t,
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            commands.entity(entity).insert(Joint::new(connections));
+        }
+
+        for ((curve, line_width), (&entity, (start, end))) in
+            self.curves.into_iter().zip(seg_entities.iter().zip(ends))
+        {
+            let segment = Segment {
+                curve,
+                start_joint: joint_entities[start],
+                end_joint: joint_entities[end],
+            };
+            commands.entity(entity).insert(segment.to_bundle(line_width));
+        }
+
+        (seg_entities, joint_entities)
+    }
+}
+
 pub trait SectionBuilder {
     /// takes in previous
     fn add_segment(self, pos: &mut Vec2, dir: &mut Vec2) -> CurveType;
@@ -231,6 +523,18 @@ pub mod builders {
         }
     }
 
+    /// Appends a straight line to `target`, turning to face it.
+    /// `target` must not be the current position.
+    pub fn line_to(target: Vec2) -> impl SectionBuilder {
+        move |pos: &mut Vec2, dir: &mut Vec2| {
+            let start = *pos;
+            *dir = (target - start).normalize();
+            *pos = target;
+
+            LineSegment { start, end: target }.into()
+        }
+    }
+
     pub fn arc_curvature_length(length: f32, curvature: f32) -> impl SectionBuilder {
         move |pos: &mut Vec2, dir: &mut Vec2| {
             let arc = ArcSegment::from_pos_dir_curvature_length(*pos, *dir, curvature, length);
@@ -256,3 +560,133 @@ struct SplineSegment {
     pub running_distance: f32,
     pub segment: CurveType,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::builder::builders::{arc, line, line_to};
+    use crate::builder::{CurveBuilder, GraphBuilder};
+    use alloc::vec;
+    use core::f32::consts::{FRAC_PI_2, PI};
+    use curve::arc::ArcSegment;
+    use curve::line::LineSegment;
+    use glam::Vec2;
+
+    #[test]
+    fn test_3_way_graph() {
+        // the layout of `test_scenes::test_3_way_curve`
+        let top_left = Vec2::new(100.0, 50.0);
+        let scale = 50.0;
+        let (top, left, right, left_top, right_top, left_right) = (0, 1, 2, 3, 4, 5);
+        let graph = GraphBuilder::new()
+            .push(
+                LineSegment {
+                    start: top_left + Vec2::new(scale * 2.0, 0.0),
+                    end: top_left + Vec2::new(scale * 2.0, scale),
+                },
+                3,
+            )
+            .push(
+                LineSegment {
+                    start: top_left + Vec2::new(0.0, scale * 2.0),
+                    end: top_left + Vec2::new(scale, scale * 2.0),
+                },
+                3,
+            )
+            .push(
+                LineSegment {
+                    start: top_left + Vec2::new(scale * 4.0, scale * 2.0),
+                    end: top_left + Vec2::new(scale * 3.0, scale * 2.0),
+                },
+                3,
+            )
+            .push(
+                ArcSegment {
+                    center: top_left + Vec2::new(scale, scale),
+                    start: -FRAC_PI_2,
+                    end: 0.0,
+                    radius: scale,
+                },
+                3,
+            )
+            .push(
+                ArcSegment {
+                    center: top_left + Vec2::new(scale * 3.0, scale),
+                    start: -FRAC_PI_2,
+                    end: -PI,
+                    radius: scale,
+                },
+                3,
+            )
+            .push(
+                LineSegment {
+                    start: top_left + Vec2::new(scale, scale * 2.0),
+                    end: top_left + Vec2::new(scale * 3.0, scale * 2.0),
+                },
+                3,
+            )
+            .graph();
+
+        let top_single = vec![vec![(top, 0.0)]];
+        let left_single = vec![vec![(left, 0.0)]];
+        let right_single = vec![vec![(right, 0.0)]];
+        let top_multi = vec![vec![(top, 1.0)], vec![(left_top, 1.0), (right_top, 1.0)]];
+        let left_multi = vec![vec![(left, 1.0)], vec![(left_right, 0.0), (left_top, 0.0)]];
+        let right_multi = vec![vec![(right, 1.0)], vec![(right_top, 0.0), (left_right, 1.0)]];
+
+        assert_eq!(
+            graph.joints,
+            vec![top_single, top_multi, left_single, left_multi, right_single, right_multi]
+        );
+        assert_eq!(graph.ends, vec![(0, 1), (2, 3), (4, 5), (3, 1), (5, 1), (3, 5)]);
+    }
+
+    #[test]
+    fn test_graph_tolerance() {
+        let graph = GraphBuilder::new()
+            .push(LineSegment { start: Vec2::ZERO, end: Vec2::new(10.0, 0.0) }, 3)
+            // within a pixel, so it shares the joint
+            .push(LineSegment { start: Vec2::new(10.5, 0.0), end: Vec2::new(20.0, 0.0) }, 3)
+            // more than 5° off, so it doesn't share the connection
+            .push(LineSegment { start: Vec2::new(10.0, 0.0), end: Vec2::new(20.0, 5.0) }, 3)
+            .graph();
+
+        assert_eq!(graph.ends, vec![(0, 1), (1, 2), (1, 3)]);
+        assert_eq!(graph.joints[1], vec![vec![(0, 1.0)], vec![(1, 0.0)], vec![(2, 0.0)]]);
+    }
+
+    #[test]
+    fn test_branch_and_join() {
+        let main = CurveBuilder::new(Vec2::ZERO, Vec2::X).push(line(50.0));
+        let fork = main.cursor();
+        let main = main.push(line(100.0));
+        let merge = main.cursor();
+        let graph = main
+            .push(line(50.0))
+            // a left turn off the main line, then straight back onto it at 45°
+            .branch_from(fork, |siding| siding.push(arc(50.0, 0.25)).join(merge))
+            .into_graph(3, false)
+            .graph();
+
+        // main line, then the arc and the line back of the branch
+        assert_eq!(graph.ends, vec![(0, 1), (1, 2), (2, 3), (1, 4), (4, 2)]);
+        // the branch leaves by the same connection as the main line, turning left of it
+        assert_eq!(graph.joints[1], vec![vec![(0, 1.0)], vec![(1, 0.0), (3, 0.0)]]);
+        // but joins at an angle, so it gets its own
+        assert_eq!(graph.joints[2], vec![vec![(1, 1.0)], vec![(2, 0.0)], vec![(4, 1.0)]]);
+    }
+
+    #[test]
+    fn test_branch_join_ends() {
+        let graph = CurveBuilder::new(Vec2::ZERO, Vec2::X)
+            .push(line(50.0))
+            .branch(|spur| spur.push(line(20.0)))
+            .push(line_to(Vec2::new(50.0, 50.0)))
+            .into_graph(3, true)
+            .graph();
+
+        // the end is joined to the start although they don't meet
+        assert_eq!(graph.ends, vec![(0, 1), (1, 0), (1, 2)]);
+        assert_eq!(graph.joints[0], vec![vec![(0, 0.0)], vec![(1, 1.0)]]);
+        assert_eq!(graph.joints.len(), 3);
+    }
+}
@@ -1,7 +1,7 @@
 use alloc::rc::Rc;
 use core::f32::consts::TAU;
 use core::mem::swap;
-use bevy_app::{App, Plugin};
+use bevy_app::{App, Plugin, PreUpdate};
 use bevy_ecs::bundle::Bundle;
 use bevy_ecs::component::Component;
 use bevy_ecs::entity::Entity;
@@ -28,7 +28,8 @@ impl Plugin for CurvePlugin {
     fn build(&self, app: &mut App) {
         app
             .register_type::<Segment>()
-            .register_type::<Joint>();
+            .register_type::<Joint>()
+            .add_systems(PreUpdate, crate::validate::validate_tracks);
     }
 }
 
@@ -145,6 +146,7 @@ impl Segment {
 
 #[derive(Clone, PartialEq, Debug, Component, Reflect)]
 #[reflect(Component)]
+#[require(Transform)]
 pub struct Joint {
     pub connections: SmallVec<[JointConnection; 2]>,
 }
@@ -234,6 +236,54 @@ impl Joint {
         let normalized = Rot2::from_sin_cos(next_dir.y, next_dir.x).inverse() * gravity_dir;
         let next_id = next.eval(Dir2::new_unchecked(normalized));
 
+        Self::exit(next_id, v, enter_vel, q_segment)
+    }
+
+    /// The segment leaving the joint at `index`, counting through the segments of each connection in order.
+    pub fn route(&self, index: usize) -> Option<(&JointConnection, &SegmentConnection)> {
+        self.connections
+            .iter()
+            .flat_map(|connection| connection.segments.iter().map(move |segment| (connection, segment)))
+            .nth(index)
+    }
+
+    /// How many segments leave the joint, so how many values [`Joint::route`] takes.
+    pub fn route_count(&self) -> usize {
+        self.connections.iter().map(|connection| connection.segments.len()).sum()
+    }
+
+    /// Like [`Joint::enter`], but leaves by [`Joint::route`] unless its connection is where the dot came from,
+    /// e.g. for a [`JointSwitch`](crate::switch::JointSwitch).
+    pub fn enter_route(
+        &self,
+        route: usize,
+        v: f32,
+        gravity_dir: Dir2,
+        enter_segment_entity: Entity,
+        t_enter: f32,
+        q_segment: &Query<CurveQuery>,
+    ) -> EnterJointResult {
+        let Some((_, next)) = self.route(route).filter(|(connection, _)| {
+            !connection
+                .segments
+                .iter()
+                .any(|i| i.id == enter_segment_entity && i.t == t_enter)
+        }) else {
+            return self.enter(v, gravity_dir, enter_segment_entity, t_enter, q_segment);
+        };
+
+        let enter_curve = q_segment.get(enter_segment_entity).unwrap();
+        let enter_vel = enter_curve.curve().dir(t_enter) * v.signum();
+        Self::exit(next, v, enter_vel, q_segment)
+    }
+
+    /// Leaves by `next_id`, keeping the speed along it.
+    fn exit(
+        next_id: &SegmentConnection,
+        v: f32,
+        enter_vel: Vec2,
+        q_segment: &Query<CurveQuery>,
+    ) -> EnterJointResult {
         // Our joint's directions are all in the same direction,
         // but might be flipped, so let's use the real one
         let next_dir = q_segment.get(next_id.id).unwrap().curve().dir(next_id.t);
@@ -292,6 +342,16 @@ impl JointConnection {
     }
 }
 
+/// Direction and curvature (positive to the left) of leaving a joint onto `curve` at `t`.
+/// Leaving from the end of a curve goes backwards along it, so it turns the other way.
+pub fn leaving(curve: &CurveType, t: f32) -> (Vec2, f32) {
+    if t == 0.0 {
+        (curve.dir(t).as_vec2(), -curve.curvature())
+    } else {
+        (-curve.dir(t).as_vec2(), curve.curvature())
+    }
+}
+
 fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
     let diff = (b - a) % TAU;
     let distance = ((2.0 * diff) % TAU) - diff;
@@ -1,20 +1,26 @@
 use crate::builder::CurveParent;
-use crate::curve::{CurveQuery, Joint};
-use crate::tiled::PlaydateReader;
+use crate::curve::{CurveQuery, Joint, Segment};
+use crate::switch::{JointSwitch, SwitchPlugin};
+use crate::tiled::{TiledMap, TiledPlugin};
 use alloc::format;
 use bevy_app::{App, Plugin, PostUpdate, Startup, Update};
 use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::{ReflectComponent, ReflectResource};
+use bevy_reflect::std_traits::ReflectDefault;
+use bevy_reflect::Reflect;
 use bevy_math::Dir2;
 use bevy_playdate::dbg;
-use bevy_playdate::debug::in_debug;
+use bevy_playdate::debug::{in_debug_channel, DebugChannel};
+use bevy_playdate::debug::profiler::profiled;
 use bevy_playdate::file::FileHandle;
 use bevy_playdate::input::CrankInput;
 use bevy_playdate::sprite::Sprite;
 use bevy_playdate::time::Time;
 use bevy_transform::prelude::Transform;
 use curve::roots::{quadratic, SolutionIter};
-use curve::traits::CurveSegment;
-use glam::{Vec2, Vec3};
+use curve::intersect::intersect;
+use curve::traits::{CurveEnd, CurveSegment, CurveSegmentSystem, CurveType};
+use glam::{Vec2, Vec3, Vec3Swizzles};
 use no_std_io2::io::BufReader;
 use num_traits::float::{Float, TotalOrder};
 use pd::fs::FileOptions;
@@ -24,7 +30,6 @@ use pd::graphics::{draw_ellipse, draw_rect, Graphics};
 use pd::sprite::draw_sprites;
 use pd::sys::ffi::LCDColor;
 use bevy_playdate::view::Camera;
-use tiled::Loader;
 
 pub struct GamePlugin;
 
@@ -32,15 +37,24 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_non_send_resource(Graphics::Cached());
         app.add_plugins(super::curve::CurvePlugin);
+        app.add_plugins(TiledPlugin);
+        app.add_plugins(SwitchPlugin);
+        app.init_resource::<DotPhysics>()
+            .register_type::<DotPhysics>()
+            .register_type::<DotBody>()
+            .register_type::<TrackMaterial>()
+            .register_type::<SpeedZone>();
 
         app.add_systems(Update, (
-            move_spline_dot,
+            profiled(move_spline_dot),
+            fly_dots,
             test_move,
         ).chain());
 
-        app.add_systems(PostUpdate, (debug_dots, debug_sprite_bounds)
-            .after(draw_sprites)
-            .run_if(in_debug));
+        app.add_systems(PostUpdate, (
+            debug_dots.run_if(in_debug_channel(DebugChannel::Physics)),
+            debug_sprite_bounds.run_if(in_debug_channel(DebugChannel::SpriteBounds)),
+        ).after(draw_sprites));
         
         app.add_systems(Startup, test_scenes);
     }
@@ -51,13 +65,13 @@ fn test_scenes(mut commands: Commands) {
         Camera,
     ));
 
-    let set = Loader::with_reader(PlaydateReader)
-        .load_tsx_tileset("test/tileset.tsx")
-        .unwrap();
+    commands.spawn(TiledMap::new("xml/test.tmx"));
     
     crate::test_scenes::test_builder(&mut commands);
     crate::test_scenes::test_branch(&mut commands);
     crate::test_scenes::test_3_way_curve(&mut commands);
+    crate::test_scenes::test_3_way_builder(&mut commands);
+    crate::test_scenes::test_track_file(&mut commands);
     crate::test_scenes::test_circle(&mut commands);
 }
 
@@ -74,12 +88,111 @@ fn test_move(
 }
 
 #[derive(Component, Debug, PartialEq, Copy, Clone)]
+#[require(DotBody, TrackContact)]
 pub struct MovingSplineDot {
     pub t: f32,
     pub v: f32,
     pub spline_entity: Entity,
 }
 
+/// Physical properties of a [`MovingSplineDot`].
+#[derive(Component, Reflect, Debug, PartialEq, Copy, Clone)]
+#[reflect(Component, Default)]
+pub struct DotBody {
+    /// Heavier dots are slowed down less by [`TrackMaterial::drag`].
+    pub mass: f32,
+    /// Speed the dot is clamped to, in pixels per second.
+    pub max_speed: f32,
+    /// How hard, in pixels per second squared, the track can pull the dot towards itself.
+    ///
+    /// A dot leaves the track when staying on it would take more, like over the top of a hill
+    /// or a loop that is too fast or slow. `0` rides on top of the track like a ball,
+    /// and infinity never leaves it like a bead on a wire.
+    pub grip: f32,
+}
+
+impl Default for DotBody {
+    fn default() -> Self {
+        DotBody {
+            mass: 1.0,
+            max_speed: 1000.0,
+            grip: f32::INFINITY,
+        }
+    }
+}
+
+/// Which side of the track a [`MovingSplineDot`] is on, see [`DotBody::grip`].
+#[derive(Component, Debug, PartialEq, Copy, Clone, Default)]
+pub struct TrackContact {
+    /// Normal of the track pointing towards the dot, or zero to pick the side gravity presses it onto.
+    pub normal: Vec2,
+}
+
+/// A dot that left the track, flying until it hits a [`Segment`].
+#[derive(Component, Debug, PartialEq, Copy, Clone)]
+#[require(DotBody, Transform)]
+pub struct FlyingDot {
+    pub velocity: Vec2,
+    /// The segment it left, which it can't land on again straight away.
+    pub from: Entity,
+    /// Seconds since it left the track.
+    pub airtime: f32,
+}
+
+/// Triggered on a dot when it leaves the track, at an open end or by going too fast (see [`DotBody::grip`]).
+#[derive(Event, Debug, PartialEq, Copy, Clone)]
+pub struct DotLaunched {
+    pub segment: Entity,
+    pub velocity: Vec2,
+}
+
+/// Triggered on a [`FlyingDot`] when it lands on a segment.
+#[derive(Event, Debug, PartialEq, Copy, Clone)]
+pub struct DotLanded {
+    pub segment: Entity,
+    pub t: f32,
+    /// Velocity right before landing. Only the part along the track is kept.
+    pub velocity: Vec2,
+}
+
+/// What a [`Segment`](crate::curve::Segment) is made of. Segments without one use [`TrackMaterial::default`].
+#[derive(Component, Reflect, Debug, PartialEq, Copy, Clone, Default)]
+#[reflect(Component, Default)]
+pub struct TrackMaterial {
+    /// Rolling resistance, as a fraction of the gravity pressing the dot onto the track.
+    pub friction: f32,
+    /// Air drag, slowing dots by `drag * v^2 / mass`.
+    pub drag: f32,
+}
+
+/// Speeds dots on a [`Segment`](crate::curve::Segment) up or, with a negative `acceleration`, down,
+/// in the direction they are moving.
+#[derive(Component, Reflect, Debug, PartialEq, Copy, Clone, Default)]
+#[reflect(Component, Default)]
+pub struct SpeedZone {
+    /// In pixels per second squared. Brakes never reverse a dot.
+    pub acceleration: f32,
+}
+
+/// Settings shared by every [`MovingSplineDot`].
+#[derive(Resource, Reflect, Debug, PartialEq, Copy, Clone)]
+#[reflect(Resource, Default)]
+pub struct DotPhysics {
+    /// In pixels per second squared.
+    pub gravity: f32,
+    /// Slows dots by `damping * v` everywhere.
+    pub damping: f32,
+}
+
+impl Default for DotPhysics {
+    fn default() -> Self {
+        DotPhysics {
+            gravity: 100.0,
+            damping: 0.05,
+        }
+    }
+}
+
 fn debug_sprite_bounds(
     sprites: Query<&Sprite>,
 ) {
@@ -97,16 +210,23 @@ fn rotate(v: Vec2, angle: f32) -> Vec2 {
     Vec2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
 }
 
+fn gravity(crank: &CrankInput, physics: &DotPhysics) -> Vec2 {
+    rotate(Vec2::NEG_Y, crank.angle.to_radians()) * physics.gravity
+}
+
 fn move_spline_dot(
-    mut dots: Query<(&mut MovingSplineDot, &mut Transform)>,
+    mut commands: Commands,
+    mut dots: Query<(Entity, &mut MovingSplineDot, &DotBody, &mut TrackContact, &mut Transform)>,
     q_segments: Query<CurveQuery>,
-    q_joints: Query<&Joint>,
+    q_joints: Query<(&Joint, Option<&JointSwitch>)>,
+    q_materials: Query<(Option<&TrackMaterial>, Option<&SpeedZone>)>,
+    physics: Res<DotPhysics>,
     crank: Res<CrankInput>,
     time: Res<Time>,
 ) {
-    let gravity = rotate(Vec2::NEG_Y, crank.angle.to_radians()) * 100.0;
+    let gravity = gravity(&crank, &physics);
 
-    for (mut dot, mut transform) in &mut dots {
+    for (entity, mut dot, body, mut contact, mut transform) in &mut dots {
         move_dot_recursive(
             dot.as_mut(),
             time.delta_secs(),
@@ -115,22 +235,184 @@ fn move_spline_dot(
             &q_segments,
             &q_joints,
         );
-        dot.v *= 0.999;
-
-        let new_pos = q_segments
+        let (material, zone) = q_materials.get(dot.spline_entity).unwrap_or_default();
+        let normal_gravity = q_segments
             .get(dot.spline_entity)
-            .unwrap()
-            // comment below line to use global transform instead of absolute position
-            // left for now because things break (change pls)
-            .curve()
-            .position(dot.t);
+            .map_or(0.0, |curve| gravity.perp_dot(curve.curve().dir(dot.t).into()).abs());
+        apply_resistance(
+            dot.as_mut(),
+            body,
+            material.copied().unwrap_or_default(),
+            zone.copied().unwrap_or_default(),
+            &physics,
+            normal_gravity,
+            time.delta_secs(),
+        );
+
+        let segment = q_segments.get(dot.spline_entity).unwrap().segment;
+        // comment below line to use global transform instead of absolute position
+        // left for now because things break (change pls)
+        let curve = &segment.curve;
+        let new_pos = curve.position(dot.t);
 
         transform.translation = new_pos.extend(0.0);
 
         // sprite.move_to(new_pos.x, new_pos.y);
+
+        let at_open_end = match dot.t {
+            0.0 => dot.v < 0.0 && is_open(segment.start_joint, &q_joints),
+            1.0 => dot.v > 0.0 && is_open(segment.end_joint, &q_joints),
+            _ => false,
+        };
+        if at_open_end || loses_grip(curve, &dot, body, &mut contact, gravity) {
+            let velocity = Vec2::from(curve.dir(dot.t)) * dot.v;
+            commands
+                .entity(entity)
+                .remove::<MovingSplineDot>()
+                .insert(FlyingDot {
+                    velocity,
+                    from: dot.spline_entity,
+                    airtime: 0.0,
+                });
+            commands.trigger_targets(
+                DotLaunched {
+                    segment: dot.spline_entity,
+                    velocity,
+                },
+                entity,
+            );
+        }
+    }
+}
+
+/// Whether a joint leads nowhere, so dots fly off it.
+fn is_open(joint: Entity, q_joints: &Query<(&Joint, Option<&JointSwitch>)>) -> bool {
+    q_joints.get(joint).is_ok_and(|(joint, _)| joint.connections.len() < 2)
+}
+
+/// Whether staying on the track would take more than the dot's grip.
+/// Also keeps track of the side of the track the dot is on in `contact`.
+fn loses_grip(curve: &CurveType, dot: &MovingSplineDot, body: &DotBody, contact: &mut TrackContact, gravity: Vec2) -> bool {
+    let dir: Vec2 = curve.dir(dot.t).into();
+    let normal = dir.perp();
+    // the curve's normal flips with its direction, so follow the side and not the sign
+    let side = if contact.normal == Vec2::ZERO {
+        if gravity.dot(normal) > 0.0 { -1.0 } else { 1.0 }
+    } else if contact.normal.dot(normal) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    let away = normal * side;
+    contact.normal = away;
+
+    if body.grip == f32::INFINITY {
+        return false;
     }
+
+    // acceleration needed to follow the curve, towards its center
+    let needed = match curve {
+        CurveType::Arc(arc) => {
+            let position = curve.position(dot.t);
+            (arc.center - position).normalize_or_zero() * dot.v * dot.v / arc.radius
+        }
+        CurveType::Line(_) => Vec2::ZERO,
+    };
+    // how hard the track has to push the dot away from itself, negative if it has to pull
+    let support = needed.dot(away) - gravity.dot(away);
+    support < -body.grip
 }
 
+/// Time after leaving a segment before a dot can land on it again.
+const LANDING_GRACE: f32 = 0.2;
+
+fn fly_dots(
+    mut commands: Commands,
+    mut dots: Query<(Entity, &mut FlyingDot, &DotBody, &mut Transform)>,
+    q_segments: Query<(Entity, &Segment)>,
+    physics: Res<DotPhysics>,
+    crank: Res<CrankInput>,
+    time: Res<Time>,
+) {
+    let gravity = gravity(&crank, &physics);
+    let dt = time.delta_secs();
+
+    for (entity, mut dot, body, mut transform) in &mut dots {
+        dot.airtime += dt;
+        dot.velocity = (dot.velocity + gravity * dt).clamp_length_max(body.max_speed);
+
+        let start = transform.translation.xy();
+        let end = start + dot.velocity * dt;
+        let hit = q_segments
+            .iter()
+            .filter(|&(e, _)| e != dot.from || dot.airtime > LANDING_GRACE)
+            .filter_map(|(e, segment)| Some((e, segment, intersect(&segment.curve, start, end)?)))
+            .min_by(|a, b| a.2.s.total_cmp(&b.2.s));
+
+        let Some((segment_entity, segment, hit)) = hit else {
+            transform.translation = end.extend(transform.translation.z);
+            continue;
+        };
+
+        let position = segment.curve.position(hit.t);
+        let dir: Vec2 = segment.curve.dir(hit.t).into();
+        let normal = dir.perp();
+        transform.translation = position.extend(transform.translation.z);
+        commands
+            .entity(entity)
+            .remove::<FlyingDot>()
+            .insert((
+                MovingSplineDot {
+                    t: hit.t,
+                    v: dot.velocity.dot(dir),
+                    spline_entity: segment_entity,
+                },
+                // it stays on the side it came from
+                TrackContact {
+                    normal: if dot.velocity.dot(normal) > 0.0 { -normal } else { normal },
+                },
+            ));
+        commands.trigger_targets(
+            DotLanded {
+                segment: segment_entity,
+                t: hit.t,
+                velocity: dot.velocity,
+            },
+            entity,
+        );
+    }
+}
+
+/// Applies the forces that aren't gravity along the track over `dt`, given the segment the dot is on
+/// and the part of gravity pressing it onto that segment.
+///
+/// Only the dot's speed changes, it doesn't move further, so these are integrated separately
+/// from gravity with a step per frame.
+fn apply_resistance(
+    dot: &mut MovingSplineDot,
+    body: &DotBody,
+    material: TrackMaterial,
+    zone: SpeedZone,
+    physics: &DotPhysics,
+    normal_gravity: f32,
+    dt: f32,
+) {
+    let speed = dot.v.abs();
+    // stationary dots start moving forwards along the segment
+    let sign = if dot.v == 0.0 { 1.0 } else { dot.v.signum() };
+
+    let slowdown = material.friction * normal_gravity
+        + material.drag * speed * speed / body.mass
+        + physics.damping * speed
+        + (-zone.acceleration).max(0.0);
+
+    let speed = (speed - slowdown * dt).max(0.0) + zone.acceleration.max(0.0) * dt;
+    dot.v = sign * speed.min(body.max_speed);
+}
+
+/// Longest step, in seconds, when integrating the motion along arcs.
+const ARC_STEP: f32 = 1.0 / 200.0;
+
 // todo:
 //  velocity direction should be kept through iterations
 //  so it is the same w/ or w/o the joint
@@ -141,7 +423,7 @@ fn move_dot_recursive(
     depth: usize,
     gravity: Vec2,
     q_segments: &Query<CurveQuery>,
-    q_joints: &Query<&Joint>,
+    q_joints: &Query<(&Joint, Option<&JointSwitch>)>,
 ) {
     if depth > 10 {
         println!("spent too long: remaining time: {}", t_remaining);
@@ -170,16 +452,26 @@ fn move_dot_recursive(
         old_t: f32,
         gravity: Vec2,
         q_segments: &Query<CurveQuery>,
-        q_joints: &Query<&Joint>,
+        q_joints: &Query<(&Joint, Option<&JointSwitch>)>,
     ) {
-        let joint = q_joints.get(new_joint).unwrap();
-        let result = joint.enter(
-            dot.v,
-            Dir2::new(gravity).unwrap(),
-            dot.spline_entity,
-            old_t,
-            q_segments,
-        );
+        let (joint, switch) = q_joints.get(new_joint).unwrap();
+        dot.t = old_t;
+        if joint.connections.len() < 2 {
+            // the dot flies off open ends, see `move_spline_dot`
+            return;
+        }
+        let gravity_dir = Dir2::new(gravity).unwrap();
+        let result = match switch {
+            Some(switch) => joint.enter_route(
+                switch.selected,
+                dot.v,
+                gravity_dir,
+                dot.spline_entity,
+                old_t,
+                q_segments,
+            ),
+            None => joint.enter(dot.v, gravity_dir, dot.spline_entity, old_t, q_segments),
+        };
 
         dot.t = result.t;
         dot.v = result.v;
@@ -207,6 +499,35 @@ fn move_dot_recursive(
         return move_dot_recursive(dot, t_remaining, depth + 1, gravity, q_segments, q_joints);
     }
 
+    // the pull of gravity along an arc changes as it turns, so it can't be solved like a line
+    if let CurveType::Arc(arc) = curve.curve() {
+        let motion = CurveSegmentSystem { curve: arc, gravity }.integrate(t, v, t_remaining, ARC_STEP);
+        dot.t = motion.t;
+        dot.v = motion.v;
+
+        let Some(end) = motion.end else {
+            return;
+        };
+        let joint = match end {
+            CurveEnd::Start => segment.start_joint,
+            CurveEnd::End => segment.end_joint,
+        };
+        let old_dot = *dot;
+        change_joints(dot, joint, end.t(), gravity, q_segments, q_joints);
+        if *dot == old_dot {
+            return;
+        }
+
+        return move_dot_recursive(
+            dot,
+            t_remaining - motion.time,
+            depth + 1,
+            gravity,
+            q_segments,
+            q_joints,
+        );
+    }
+
     // solve t = 1
     // => 1/2 * g * t^2 + v * t + t_0 = 1
     // => ... - 1 = 0
@@ -224,7 +545,7 @@ fn move_dot_recursive(
         .next();
 
     if let Some((time, t_old, joint)) = end {
-        dot.v += g * time / length;
+        dot.v += g * time;
 
         let old_dot = *dot;
         change_joints(dot, joint, t_old, gravity, q_segments, q_joints);
@@ -256,7 +577,7 @@ fn move_dot_2(
     dot: &mut MovingSplineDot,
     gravity: Vec2,
     q_segments: &Query<CurveQuery>,
-    q_joints: &Query<&Joint>,
+    q_joints: &Query<(&Joint, Option<&JointSwitch>)>,
     time: f32,
 ) {
     let segment = q_segments.get(dot.spline_entity).unwrap().segment;
@@ -277,7 +598,7 @@ fn move_dot_2(
     while dot.t > 1.0 {
         dot.t -= 1.0;
 
-        let joint = q_joints.get(segment.end_joint).unwrap();
+        let (joint, _) = q_joints.get(segment.end_joint).unwrap();
         let result = joint.enter(
             dot.v,
             Dir2::new(gravity).unwrap(),
@@ -9,8 +9,11 @@ mod builder;
 mod curve;
 mod game;
 mod ui_test;
+mod switch;
 mod test_scenes;
 mod tiled;
+mod track_file;
+mod validate;
 
 use bevy_app::{App, PostUpdate};
 use bevy_playdate::{DefaultPlugins, event::SystemEvent};
@@ -0,0 +1,206 @@
+//! Joints that send dots down a chosen route instead of the one best aligned with gravity.
+
+use crate::curve::{leaving, Joint, Segment};
+use bevy_app::{App, Plugin, PreUpdate, Update};
+use bevy_ecs::component::HookContext;
+use bevy_ecs::name::Name;
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_ecs::world::DeferredWorld;
+use bevy_playdate::input::{ButtonInput, CrankInput, PdButton, PdInputSystem};
+use bevy_playdate::sprite::Sprite;
+use bevy_reflect::Reflect;
+use bevy_transform::prelude::Transform;
+use curve::traits::CurveSegment;
+use pd::graphics::bitmap::Color;
+use pd::graphics::color::LCDColorConst;
+use pd::sys::ffi::LCDColor;
+
+/// Adds [`JointSwitch`]es, toggled by their [`SwitchControl`] or a [`ToggleSwitch`] trigger.
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<JointSwitch>()
+            .add_systems(PreUpdate, control_switches.after(PdInputSystem))
+            .add_systems(Update, show_switches)
+            .add_observer(toggle_switch);
+    }
+}
+
+/// Makes dots leave a [`Joint`] by the selected segment, unless its connection is where they came from.
+///
+/// A [`Sprite`] marking the selected route is spawned next to the joint, as a child of the joint's
+/// parent ([`CurveParent`](crate::builder::CurveParent)) so it is in the same space as the segments.
+/// It is despawned with the switch.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+#[component(on_remove = despawn_indicator)]
+pub struct JointSwitch {
+    /// Index of the segment, see [`Joint::route`].
+    pub selected: usize,
+    pub control: SwitchControl,
+    /// Crank rotation since the last tick, for [`SwitchControl::Crank`].
+    #[reflect(ignore)]
+    cranked: f32,
+    /// The entity showing the selected route.
+    #[reflect(ignore)]
+    indicator: Option<Entity>,
+}
+
+impl JointSwitch {
+    pub fn new(selected: usize, control: SwitchControl) -> Self {
+        Self {
+            selected,
+            control,
+            cranked: 0.0,
+            indicator: None,
+        }
+    }
+
+    /// Selects the route `steps` after the current one, wrapping around the `routes` of the joint.
+    pub fn step(&mut self, steps: isize, routes: usize) {
+        if routes > 0 {
+            self.selected = (self.selected as isize + steps).rem_euclid(routes as isize) as usize;
+        }
+    }
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq)]
+pub enum SwitchControl {
+    /// Selects the next route when the button is pressed.
+    Button(PdButton),
+    /// Selects the next route every time the crank turns clockwise by `degrees`,
+    /// and the previous one when it turns back.
+    Crank { degrees: f32 },
+    /// Only changed by [`ToggleSwitch`] or by setting [`JointSwitch::selected`].
+    Trigger,
+}
+
+fn despawn_indicator(mut w: DeferredWorld, HookContext { entity: e, .. }: HookContext) {
+    if let Some(indicator) = w.get::<JointSwitch>(e).and_then(|switch| switch.indicator) {
+        w.commands().entity(indicator).try_despawn();
+    }
+}
+
+/// Trigger on a joint with a [`JointSwitch`] to select its next route.
+#[derive(Event, Copy, Clone, Debug, PartialEq)]
+pub struct ToggleSwitch;
+
+fn control_switches(
+    buttons: Res<ButtonInput>,
+    crank: Res<CrankInput>,
+    mut q_switches: Query<(&mut JointSwitch, &Joint)>,
+) {
+    for (mut switch, joint) in &mut q_switches {
+        let routes = joint.route_count();
+        match switch.control {
+            SwitchControl::Button(button) => {
+                if buttons.just_pressed(button) {
+                    switch.step(1, routes);
+                }
+            }
+            SwitchControl::Crank { degrees } if degrees > 0.0 && crank.change != 0.0 => {
+                switch.cranked += crank.change;
+                let ticks = (switch.cranked / degrees) as isize;
+                if ticks != 0 {
+                    switch.cranked -= ticks as f32 * degrees;
+                    switch.step(ticks, routes);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn toggle_switch(
+    trigger: Trigger<ToggleSwitch>,
+    mut q_switches: Query<(&mut JointSwitch, &Joint)>,
+) {
+    if let Ok((mut switch, joint)) = q_switches.get_mut(trigger.target()) {
+        switch.step(1, joint.route_count());
+    }
+}
+
+/// How far from the joint the indicator is drawn along the selected route.
+const INDICATOR_DISTANCE: f32 = 10.0;
+
+/// Moves each switch's indicator onto its selected route, spawning it the first time.
+fn show_switches(
+    mut commands: Commands,
+    mut q_switches: Query<(Entity, &mut JointSwitch, &Joint), Or<(Changed<JointSwitch>, Changed<Joint>)>>,
+    q_segments: Query<&Segment>,
+    q_parents: Query<&ChildOf>,
+    mut sprite: Local<Option<Sprite>>,
+) {
+    for (entity, mut switch, joint) in &mut q_switches {
+        let Some((_, route)) = joint.route(switch.selected) else {
+            continue;
+        };
+        let Ok(segment) = q_segments.get(route.id) else {
+            continue;
+        };
+
+        let (dir, _) = leaving(&segment.curve, route.t);
+        let position = segment.curve.position(route.t) + dir * INDICATOR_DISTANCE;
+        let transform = Transform::from_translation(position.extend(0.0));
+
+        match switch.indicator {
+            Some(indicator) => {
+                commands.entity(indicator).insert(transform);
+            }
+            None => {
+                let sprite = sprite.get_or_insert_with(|| {
+                    Sprite::new_from_draw(6, 6, Color::CLEAR, |gfx| {
+                        gfx.draw_ellipse(0, 0, 6, 6, 3, 0.0, 0.0, LCDColor::BLACK);
+                    })
+                });
+                let indicator = commands.spawn((Name::new("Switch Indicator"), sprite.clone(), transform)).id();
+                // joints spawned without a track parent are already in world space
+                if let Ok(child_of) = q_parents.get(entity) {
+                    commands.entity(child_of.parent()).add_child(indicator);
+                }
+                // only bookkeeping, don't show the switch as changed again
+                switch.bypass_change_detection().indicator = Some(indicator);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::curve::{Joint, JointConnection, SegmentConnection};
+    use crate::switch::{JointSwitch, SwitchControl};
+    use bevy_ecs::world::World;
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_routes() {
+        let mut world = World::new();
+        let [back, right, up] = [(); 3].map(|_| world.spawn_empty().id());
+        let joint = Joint::new(smallvec![
+            JointConnection {
+                segments: smallvec![SegmentConnection { id: back, t: 1.0 }],
+            },
+            JointConnection {
+                segments: smallvec![
+                    SegmentConnection { id: right, t: 0.0 },
+                    SegmentConnection { id: up, t: 0.0 },
+                ],
+            },
+        ]);
+
+        assert_eq!(joint.route_count(), 3);
+        let routes: alloc::vec::Vec<_> = (0..3).map(|i| joint.route(i).unwrap().1.id).collect();
+        assert_eq!(routes, [back, right, up]);
+        // both branches of the fork share a connection
+        assert_eq!(joint.route(1).unwrap().0, joint.route(2).unwrap().0);
+        assert!(joint.route(3).is_none());
+
+        let mut switch = JointSwitch::new(2, SwitchControl::Trigger);
+        switch.step(1, joint.route_count());
+        assert_eq!(switch.selected, 0);
+        switch.step(-2, joint.route_count());
+        assert_eq!(switch.selected, 1);
+    }
+}
@@ -2,10 +2,14 @@
 use crate::builder::CurveBuilder;
 use crate::curve::{Joint, JointConnection, Segment, SegmentConnection};
 use crate::game::MovingSplineDot;
+use crate::switch::{JointSwitch, SwitchControl};
+use crate::track_file::TrackFile;
 use alloc::vec::Vec;
 use bevy_ecs::entity::Entity;
 use bevy_ecs::name::Name;
 use bevy_ecs::prelude::Commands;
+use bevy_playdate::error;
+use bevy_playdate::input::PdButton;
 use bevy_playdate::sprite::Sprite;
 use bevy_transform::components::Transform;
 use core::cell::LazyCell;
@@ -165,6 +169,49 @@ pub fn test_branch(commands: &mut Commands) {
     ));
 }
 
+/// Same track as [`test_3_way_curve`], below it, built with branches instead of by hand.
+pub fn test_3_way_builder(commands: &mut Commands) {
+    let scale = 50.0;
+    let track = CurveBuilder::new(Vec2::new(100.0, 200.0), Vec2::X)
+        .push(line(scale))
+        // up to the top
+        .branch(|top| top.push(arc(scale, 0.25)).push(line(scale)))
+        .push(line(scale * 2.0));
+    let right_multi = track.cursor();
+
+    // segments of the main line come first: left, left-right, right
+    let (segments, _) = track
+        // back from the right joint, merging with the other arc at the top
+        .branch_from(right_multi.reversed(), |top| top.push(arc(scale, -0.25)))
+        .push(line(scale))
+        .build(commands, 3, false);
+
+    let mut sprite = CIRCLE.clone();
+
+    sprite.set_z_index(10);
+
+    commands.spawn((
+        Name::new("Dot (3-way builder)"),
+        sprite,
+        Transform::default(),
+        MovingSplineDot {
+            t: 0.0,
+            v: 0.0,
+            spline_entity: segments[0],
+        },
+    ));
+}
+
+/// The 3-way track again, loaded from `assets/3way.track`.
+pub fn test_track_file(commands: &mut Commands) {
+    match TrackFile::load("tracks/3way.track") {
+        Ok(track) => {
+            track.spawn(commands);
+        }
+        Err(e) => error!("tracks/3way.track: {e}"),
+    }
+}
+
 pub fn test_3_way_curve(commands: &mut Commands) {
     let top_segment = commands.spawn(Name::new("Segment (Top)")).id();
     let left_segment = commands.spawn(Name::new("Segment (Left)")).id();
@@ -310,28 +357,33 @@ pub fn test_3_way_curve(commands: &mut Commands) {
         ],
     });
 
-    commands.entity(left_multi_joint).insert(Joint {
-        connections: smallvec![
-            JointConnection {
-                segments: smallvec![SegmentConnection {
-                    id: left_segment,
-                    t: 1.0,
-                }],
-            },
-            JointConnection {
-                segments: smallvec![
-                    SegmentConnection {
-                        id: left_right_segment,
-                        t: 0.0,
-                    },
-                    SegmentConnection {
-                        id: left_top_segment,
-                        t: 0.0,
-                    },
-                ],
-            },
-        ],
-    });
+    // A cycles the dots coming from the left through going right (route 1), up (route 2)
+    // and following gravity (route 0, back where they came from)
+    commands.entity(left_multi_joint).insert((
+        JointSwitch::new(1, SwitchControl::Button(PdButton::A)),
+        Joint {
+            connections: smallvec![
+                JointConnection {
+                    segments: smallvec![SegmentConnection {
+                        id: left_segment,
+                        t: 1.0,
+                    }],
+                },
+                JointConnection {
+                    segments: smallvec![
+                        SegmentConnection {
+                            id: left_right_segment,
+                            t: 0.0,
+                        },
+                        SegmentConnection {
+                            id: left_top_segment,
+                            t: 0.0,
+                        },
+                    ],
+                },
+            ],
+        },
+    ));
 
     commands.entity(right_multi_joint).insert(Joint {
         connections: smallvec![
@@ -1,6 +1,32 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bevy_app::{App, Plugin};
+use bevy_ecs::component::HookContext;
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::DeferredWorld;
+use bevy_math::Quat;
+use bevy_playdate::collider::Collider;
+use bevy_playdate::error;
 use bevy_playdate::file::FileHandle;
+use bevy_playdate::fs::Location;
+use bevy_playdate::sprite::Sprite;
+use bevy_playdate::vfs::{OpenMode, Vfs, VfsFile};
+use bevy_reflect::Reflect;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_transform::prelude::Transform;
+use curve::traits::CurveType;
+use glam::{Vec2, Vec3};
 use pd::fs::FileOptions;
-use tiled::ResourceReader;
+use pd::graphics::bitmap::{Bitmap, Color};
+use pd::graphics::{BitmapFlip, Graphics};
+use tracks::TiledTrack;
+use tiled::{Layer, ObjectData, ObjectShape, PropertyValue, LayerTile, LayerType, Loader, Map, ResourceReader, TileLayer};
+
+pub mod properties;
+pub mod tracks;
 
 pub struct PlaydateReader;
 
@@ -13,3 +39,576 @@ impl ResourceReader for PlaydateReader {
         FileHandle::open(path, FileOptions::kFileRead)
     }
 }
+
+/// Reads Tiled files from the bundle through a [`Vfs`],
+/// so maps can also be loaded from a `MemoryFs` off-device.
+pub struct VfsReader(pub Vfs);
+
+impl ResourceReader for VfsReader {
+    type Resource = VfsFile;
+
+    type Error = no_std_io2::io::Error;
+
+    fn read_from(&mut self, path: &tiled::ResourcePath) -> core::result::Result<Self::Resource, Self::Error> {
+        self.0.open(path, OpenMode::Read(Location::Bundle))
+    }
+}
+
+/// Spawns the contents of [`TiledMap`]s when they are added.
+pub struct TiledPlugin;
+
+impl Plugin for TiledPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TiledMap>()
+            .register_type::<TiledLayer>()
+            .register_type::<TiledTile>()
+            .register_type::<TiledObject>()
+            .register_type::<TiledTrack>();
+    }
+}
+
+/// Loads the `.tmx` map at `path` from the bundle and spawns its layers as children of this entity.
+///
+/// Tile layers are drawn into one sprite each, and objects become entities
+/// with a [`Transform`], [`Name`] and [`TiledObject`]. Objects with the class [`TiledTrack`]
+/// are also spawned as tracks, see [`tracks`]. The tracks of every layer share one graph, spawned as a
+/// [`CurveParent`](crate::builder::CurveParent) child of this entity, in map space like the layers.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+#[require(Transform)]
+#[component(on_add = load_map)]
+pub struct TiledMap {
+    pub path: String,
+}
+
+impl TiledMap {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// A layer spawned from a [`TiledMap`].
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct TiledLayer {
+    pub id: u32,
+}
+
+/// A tile with a class or class properties, spawned as its own entity
+/// so it can hold the components they describe. See [`properties`].
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct TiledTile {
+    pub tileset: usize,
+    pub id: u32,
+}
+
+/// An object spawned from an object layer of a [`TiledMap`].
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct TiledObject {
+    pub id: u32,
+    /// The object's class (called type before Tiled 1.9).
+    pub class: String,
+}
+
+fn load_map(mut w: DeferredWorld, HookContext { entity: e, .. }: HookContext) {
+    w.commands().queue(move |world: &mut World| spawn_map(world, e));
+}
+
+fn spawn_map(world: &mut World, root: Entity) {
+    let Some(path) = world.get::<TiledMap>(root).map(|map| map.path.clone()) else {
+        return;
+    };
+
+    let map = match Loader::with_reader(VfsReader(Vfs::of(world))).load_tmx_map(&path) {
+        Ok(map) => map,
+        Err(e) => {
+            error!("failed to load {path}: {e:?}");
+            return;
+        }
+    };
+
+    if !world.entity(root).contains::<Name>() {
+        world.entity_mut(root).insert(Name::new(path.clone()));
+    }
+
+    let mut tiles = TileCache::default();
+    let mut tracks = Vec::new();
+    let layers: Vec<Entity> = map
+        .layers()
+        .map(|layer| spawn_layer(world, &map, layer, &mut tiles, Vec2::ZERO, &mut tracks))
+        .collect();
+    world.entity_mut(root).add_children(&layers);
+
+    if !tracks.is_empty() {
+        let tracks = tracks::spawn_track_graph(world, tracks);
+        world.entity_mut(root).add_child(tracks);
+    }
+}
+
+/// Spawns `layer`, whose parent layers are at `offset` from the map's origin.
+/// The curves of its tracks are added to `tracks`, in map space.
+fn spawn_layer(
+    world: &mut World,
+    map: &Map,
+    layer: Layer,
+    tiles: &mut TileCache,
+    offset: Vec2,
+    tracks: &mut Vec<(CurveType, i32)>,
+) -> Entity {
+    let transform = Transform::from_translation(Vec3::new(layer.offset_x, layer.offset_y, 0.0));
+    let offset = offset + Vec2::new(layer.offset_x, layer.offset_y);
+    let entity = world
+        .spawn((Name::new(layer.name.clone()), TiledLayer { id: layer.id() }, transform))
+        .id();
+
+    match layer.layer_type() {
+        LayerType::Tiles(tile_layer) => {
+            if let Some(sprite) = draw_tile_layer(map, &tile_layer, tiles) {
+                world.entity_mut(entity).insert(sprite);
+            }
+            let tile_entities = spawn_tile_entities(world, map, &tile_layer);
+            world.entity_mut(entity).add_children(&tile_entities);
+        }
+        LayerType::Objects(objects) => {
+            let objects: Vec<Entity> = objects
+                .objects()
+                .map(|object| {
+                    let transform = object_transform(&object);
+                    let entity = world
+                        .spawn((
+                            Name::new(object.name.clone()),
+                            TiledObject {
+                                id: object.id(),
+                                class: object.user_type.clone(),
+                            },
+                            transform,
+                        ))
+                        .id();
+                    insert_properties(world, entity, &object.name, &object.user_type, &object.properties);
+                    if let Some(track) = world.get::<TiledTrack>(entity) {
+                        let width = track.line_width;
+                        match track_curves(track, &object, offset) {
+                            Ok(curves) => tracks.extend(curves.into_iter().map(|curve| (curve, width))),
+                            Err(e) => error!("track {}: {e}", object.name),
+                        }
+                    } else if let Some(collider) = collider(&object.shape) {
+                        world.entity_mut(entity).insert(collider);
+                    }
+                    entity
+                })
+                .collect();
+            world.entity_mut(entity).add_children(&objects);
+        }
+        LayerType::Image(image_layer) => {
+            if let Some(bitmap) = image_layer.image.as_ref().and_then(|image| tiles.load(&image.source)) {
+                let sprite = Sprite::new_from_bitmap(bitmap, BitmapFlip::kBitmapUnflipped);
+                sprite.set_center(0.0, 0.0);
+                world.entity_mut(entity).insert(sprite);
+            }
+        }
+        LayerType::Group(group) => {
+            let children: Vec<Entity> = group
+                .layers()
+                .map(|layer| spawn_layer(world, map, layer, tiles, offset, tracks))
+                .collect();
+            world.entity_mut(entity).add_children(&children);
+        }
+    }
+
+    entity
+}
+
+/// Spawns an entity for each tile that has a class, class properties or collision shapes,
+/// at the top left corner of its image.
+fn spawn_tile_entities(world: &mut World, map: &Map, layer: &TileLayer) -> Vec<Entity> {
+    let TileLayer::Finite(layer) = layer else {
+        return Vec::new();
+    };
+
+    let mut entities = Vec::new();
+    for y in 0..layer.height() as i32 {
+        for x in 0..layer.width() as i32 {
+            let Some(tile) = layer.get_tile(x, y) else {
+                continue;
+            };
+            let Some(data) = tile.get_tile() else {
+                continue;
+            };
+            let class = data.user_type.as_deref().unwrap_or_default();
+            let has_components = !class.is_empty()
+                || data
+                    .properties
+                    .values()
+                    .any(|value| matches!(value, PropertyValue::ClassValue { .. }));
+            let shapes = data.collision.as_ref().map_or(&[][..], |group| group.object_data());
+            if !has_components && shapes.is_empty() {
+                continue;
+            }
+
+            let name = format!("{} ({x}, {y})", tile.get_tileset().name);
+            // tiles are drawn anchored at the bottom left of their cell, see `draw_tile_layer`
+            let height = data.image.as_ref().map_or(map.tile_height, |image| image.height as u32);
+            let position = Vec3::new(
+                (x as u32 * map.tile_width) as f32,
+                ((y as u32 + 1) * map.tile_height) as f32 - height as f32,
+                0.0,
+            );
+            let entity = world
+                .spawn((
+                    Name::new(name.clone()),
+                    TiledTile {
+                        tileset: tile.tileset_index(),
+                        id: tile.id(),
+                    },
+                    Transform::from_translation(position),
+                ))
+                .id();
+            insert_properties(world, entity, &name, class, &data.properties);
+
+            // in a tile's collision editor, any shape (usually named `collision` or `collider`) collides
+            let width = data.image.as_ref().map_or(map.tile_width, |image| image.width as u32);
+            let size = Vec2::new(width as f32, height as f32);
+            let colliders: Vec<Entity> = shapes
+                .iter()
+                .filter_map(|shape| {
+                    let (transform, collider) = tile_collider(shape, size, tile.flip_h, tile.flip_v)?;
+                    let name = if shape.name.is_empty() { "collider" } else { &shape.name };
+                    Some(world.spawn((Name::new(String::from(name)), transform, collider)).id())
+                })
+                .collect();
+            world.entity_mut(entity).add_children(&colliders);
+
+            entities.push(entity);
+        }
+    }
+    entities
+}
+
+/// Position and rotation of an object, relative to its layer or tile.
+fn object_transform(object: &ObjectData) -> Transform {
+    Transform::from_translation(Vec3::new(object.x, object.y, 0.0))
+        // Tiled rotates clockwise in degrees, which is also clockwise on screen here
+        .with_rotation(Quat::from_rotation_z(object.rotation.to_radians()))
+}
+
+/// The collider of a tile's collision shape and its transform, relative to the tile,
+/// mirrored within the tile's `size` like its image is drawn, see [`flip`].
+fn tile_collider(shape: &ObjectData, size: Vec2, flip_h: bool, flip_v: bool) -> Option<(Transform, Collider)> {
+    let collider = collider(&shape.shape)?;
+    let transform = object_transform(shape);
+    if !flip_h && !flip_v {
+        return Some((transform, collider));
+    }
+
+    let scale = Vec2::new(if flip_h { -1.0 } else { 1.0 }, if flip_v { -1.0 } else { 1.0 });
+    let mirror = |p: Vec2| p * scale + size * (Vec2::ONE - scale) / 2.0;
+
+    match collider {
+        // unrotated rects and ellipses stay the same shape, extending from their new top left
+        Collider::Rect { size: extent } | Collider::Ellipse { size: extent } if shape.rotation == 0.0 => {
+            let origin = Vec2::new(shape.x, shape.y);
+            let corner = mirror(origin).min(mirror(origin + extent));
+            Some((Transform::from_translation(corner.extend(0.0)), collider))
+        }
+        _ => {
+            let mut points: Vec<Vec2> = collider
+                .outline()
+                .into_iter()
+                .map(|p| mirror(transform.transform_point(p.extend(0.0)).truncate()))
+                .collect();
+            // mirroring one axis turns the outline inside out
+            if flip_h != flip_v {
+                points.reverse();
+            }
+            Some((Transform::default(), Collider::Polygon { points }))
+        }
+    }
+}
+
+/// The curves of a track object, in map space given the `offset` of its layer.
+fn track_curves(track: &TiledTrack, object: &ObjectData, offset: Vec2) -> Result<Vec<CurveType>, String> {
+    let (points, closed) = match object.shape {
+        ObjectShape::Polyline { ref points } => (points, false),
+        ObjectShape::Polygon { ref points } => (points, true),
+        _ => return Err("only polylines and polygons can be tracks".into()),
+    };
+    let rotation = Vec2::from_angle(object.rotation.to_radians());
+    let origin = offset + Vec2::new(object.x, object.y);
+    let points: Vec<Vec2> = points
+        .iter()
+        .map(|&(x, y)| origin + rotation.rotate(Vec2::new(x, y)))
+        .collect();
+    track.curves(&points, closed)
+}
+
+/// The collider for an object's shape, relative to the object's transform.
+/// Points, polylines, text and empty rects don't collide.
+fn collider(shape: &ObjectShape) -> Option<Collider> {
+    let points = |points: &[(f32, f32)]| points.iter().map(|&(x, y)| Vec2::new(x, y)).collect();
+    match *shape {
+        ObjectShape::Rect { width, height } if width > 0.0 && height > 0.0 => Some(Collider::Rect {
+            size: Vec2::new(width, height),
+        }),
+        ObjectShape::Ellipse { width, height } if width > 0.0 && height > 0.0 => Some(Collider::Ellipse {
+            size: Vec2::new(width, height),
+        }),
+        ObjectShape::Polygon { points: ref p } => Some(Collider::Polygon { points: points(p) }),
+        _ => None,
+    }
+}
+
+/// Inserts the components described by `properties`, logging what couldn't be.
+fn insert_properties(world: &mut World, entity: Entity, name: &str, class: &str, properties: &tiled::Properties) {
+    if let Err(errors) = properties::insert_properties(world, entity, class, properties) {
+        for e in errors {
+            error!("tiled properties of {name}: {e}");
+        }
+    }
+}
+
+/// Draws every tile of a finite layer into a single sprite, with its top left corner at the layer's origin.
+fn draw_tile_layer(map: &Map, layer: &TileLayer, tiles: &mut TileCache) -> Option<Sprite> {
+    let TileLayer::Finite(layer) = layer else {
+        error!("infinite tile layers are not supported");
+        return None;
+    };
+
+    let (tile_width, tile_height) = (map.tile_width as i32, map.tile_height as i32);
+    let bitmap = Bitmap::new(
+        layer.width() as i32 * tile_width,
+        layer.height() as i32 * tile_height,
+        Color::CLEAR,
+    )
+    .ok()?;
+
+    // load every tile before pushing the context, so nothing draws into the layer by accident
+    let mut placed = Vec::new();
+    for y in 0..layer.height() as i32 {
+        for x in 0..layer.width() as i32 {
+            let Some(tile) = layer.get_tile(x, y) else {
+                continue;
+            };
+            if let Some(image) = tiles.tile(&tile) {
+                placed.push((x, y, image, flip(&tile)));
+            }
+        }
+    }
+
+    let gfx = Graphics::Cached();
+    gfx.push_context(&bitmap);
+    for (x, y, image, flip) in placed {
+        // larger tiles (e.g. from image collections) are anchored at the bottom left of their cell
+        let (_, height) = image.size();
+        gfx.draw(&image, x * tile_width, (y + 1) * tile_height - height, flip);
+    }
+    gfx.pop_context();
+
+    let sprite = Sprite::new_from_bitmap(Rc::new(bitmap), BitmapFlip::kBitmapUnflipped);
+    sprite.set_center(0.0, 0.0);
+    Some(sprite)
+}
+
+fn flip(tile: &LayerTile) -> BitmapFlip {
+    match (tile.flip_h, tile.flip_v) {
+        (false, false) => BitmapFlip::kBitmapUnflipped,
+        (true, false) => BitmapFlip::kBitmapFlippedX,
+        (false, true) => BitmapFlip::kBitmapFlippedY,
+        (true, true) => BitmapFlip::kBitmapFlippedXY,
+    }
+}
+
+/// Bitmaps of the tiles used by a map, loaded once each.
+/// A failed load is cached too, so it is only reported once.
+#[derive(Default)]
+struct TileCache {
+    images: BTreeMap<String, Option<Rc<Bitmap>>>,
+    /// Tiles cut out of tileset atlases, by tileset index and tile id.
+    tiles: BTreeMap<(usize, u32), Option<Rc<Bitmap>>>,
+}
+
+impl TileCache {
+    /// Loads the bitmap compiled from the image at `path`.
+    fn load(&mut self, path: &str) -> Option<Rc<Bitmap>> {
+        self.images
+            .entry(path.into())
+            .or_insert_with(|| {
+                // pdc compiles images to .pdi, which is loaded without an extension
+                let pdi = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+                match Bitmap::load(pdi) {
+                    Ok(bitmap) => Some(Rc::new(bitmap)),
+                    Err(e) => {
+                        error!("failed to load {path}: {e:?}");
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    fn tile(&mut self, tile: &LayerTile) -> Option<Rc<Bitmap>> {
+        let tileset = tile.get_tileset();
+
+        // image collection tilesets have an image per tile
+        if let Some(image) = tile.get_tile().and_then(|t| t.image.clone()) {
+            return self.load(&image.source);
+        }
+
+        let key = (tile.tileset_index(), tile.id());
+        if let Some(cached) = self.tiles.get(&key) {
+            return cached.clone();
+        }
+
+        let atlas = tileset.image.as_ref().and_then(|image| self.load(&image.source));
+        let cut = atlas.and_then(|atlas| {
+            let columns = tileset.columns.max(1);
+            let (column, row) = (tile.id() % columns, tile.id() / columns);
+            let x = tileset.margin + column * (tileset.tile_width + tileset.spacing);
+            let y = tileset.margin + row * (tileset.tile_height + tileset.spacing);
+
+            let bitmap = Bitmap::new(tileset.tile_width as i32, tileset.tile_height as i32, Color::CLEAR).ok()?;
+            let gfx = Graphics::Cached();
+            gfx.push_context(&bitmap);
+            gfx.draw(&atlas, -(x as i32), -(y as i32), BitmapFlip::kBitmapUnflipped);
+            gfx.pop_context();
+            Some(Rc::new(bitmap))
+        });
+
+        if cut.is_none() {
+            error!("tile {} of tileset {} has no image", tile.id(), tileset.name);
+        }
+        self.tiles.insert(key, cut.clone());
+        cut
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tiled::{tile_collider, VfsReader};
+    use alloc::vec::Vec;
+    use bevy_playdate::collider::Collider;
+    use bevy_playdate::vfs::{MemoryFs, Vfs};
+    use glam::{Vec2, Vec3};
+    use tiled::{Loader, ObjectData, Tileset};
+
+    #[test]
+    fn test_load_map_from_memory() {
+        let memory = MemoryFs::default();
+        memory.insert_bundle(
+            "xml/test.tmx",
+            include_bytes!("../assets/colliders_and_user_properties.export.tmx").as_slice(),
+        );
+        let mut loader = Loader::with_reader(VfsReader(Vfs::new(memory)));
+
+        let map = loader.load_tmx_map("xml/test.tmx").unwrap();
+        assert_eq!((map.width, map.height), (20, 20));
+        assert_eq!((map.tile_width, map.tile_height), (32, 32));
+        assert_eq!(map.tilesets().len(), 2);
+        assert_eq!(map.layers().count(), 3);
+
+        assert!(loader.load_tmx_map("xml/missing.tmx").is_err());
+    }
+
+    /// A 16x16 tileset with a rect on tile 0, and a triangle rotated a quarter turn on tile 1.
+    const TILESET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="test" tilewidth="16" tileheight="16" tilecount="2" columns="2">
+ <image source="test.png" width="32" height="16"/>
+ <tile id="0">
+  <objectgroup draworder="index" id="2">
+   <object id="1" x="2" y="1" width="8" height="6"/>
+  </objectgroup>
+ </tile>
+ <tile id="1">
+  <objectgroup draworder="index" id="2">
+   <object id="1" x="16" y="0" rotation="90">
+    <polygon points="0,0 8,0 0,4"/>
+   </object>
+  </objectgroup>
+ </tile>
+</tileset>
+"#;
+
+    fn tileset() -> Tileset {
+        let memory = MemoryFs::default();
+        memory.insert_bundle("xml/test.tsx", TILESET.as_bytes());
+        Loader::with_reader(VfsReader(Vfs::new(memory)))
+            .load_tsx_tileset("xml/test.tsx")
+            .unwrap()
+    }
+
+    fn shape(tileset: &Tileset, id: u32) -> ObjectData {
+        let tile = tileset.get_tile(id).unwrap();
+        tile.collision.as_ref().unwrap().object_data()[0].clone()
+    }
+
+    /// Twice the signed area, positive for clockwise on screen.
+    fn winding(points: &[Vec2]) -> f32 {
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.perp_dot(*b))
+            .sum()
+    }
+
+    fn assert_points(points: &[Vec2], expected: &[Vec2]) {
+        assert_eq!(points.len(), expected.len());
+        for (p, e) in points.iter().zip(expected) {
+            assert!(p.abs_diff_eq(*e, 1e-4), "{points:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_tile_collider_rect() {
+        let tileset = tileset();
+        let rect = &shape(&tileset, 0);
+        let size = Vec2::splat(16.0);
+        let collider = Collider::Rect { size: Vec2::new(8.0, 6.0) };
+
+        for (flip_h, flip_v, corner) in [
+            (false, false, Vec2::new(2.0, 1.0)),
+            (true, false, Vec2::new(6.0, 1.0)),
+            (false, true, Vec2::new(2.0, 9.0)),
+            (true, true, Vec2::new(6.0, 9.0)),
+        ] {
+            let (transform, flipped) = tile_collider(rect, size, flip_h, flip_v).unwrap();
+            assert_eq!(flipped, collider);
+            assert_eq!(transform.translation, corner.extend(0.0));
+            assert_eq!(transform.rotation, Default::default());
+        }
+    }
+
+    #[test]
+    fn test_tile_collider_rotated_polygon() {
+        let tileset = tileset();
+        let triangle = &shape(&tileset, 1);
+        let size = Vec2::splat(16.0);
+
+        let (transform, collider) = tile_collider(triangle, size, false, false).unwrap();
+        assert_eq!(transform.translation, Vec3::new(16.0, 0.0, 0.0));
+        let Collider::Polygon { points } = collider else {
+            panic!("expected a polygon, found {collider:?}");
+        };
+        let unflipped: Vec<Vec2> = points
+            .iter()
+            .map(|p| transform.transform_point(p.extend(0.0)).truncate())
+            .collect();
+        assert_points(&unflipped, &[Vec2::new(16.0, 0.0), Vec2::new(16.0, 8.0), Vec2::new(12.0, 0.0)]);
+
+        for (flip_h, flip_v, expected) in [
+            // mirroring one axis reverses the points to keep the winding
+            (true, false, [Vec2::new(4.0, 0.0), Vec2::new(0.0, 8.0), Vec2::new(0.0, 0.0)]),
+            (false, true, [Vec2::new(12.0, 16.0), Vec2::new(16.0, 8.0), Vec2::new(16.0, 16.0)]),
+            // mirroring both is a half turn, which keeps it
+            (true, true, [Vec2::new(0.0, 16.0), Vec2::new(0.0, 8.0), Vec2::new(4.0, 16.0)]),
+        ] {
+            let (transform, collider) = tile_collider(triangle, size, flip_h, flip_v).unwrap();
+            assert_eq!(transform, Default::default());
+            let Collider::Polygon { points } = collider else {
+                panic!("expected a polygon, found {collider:?}");
+            };
+            assert_points(&points, &expected);
+            assert_eq!(winding(&points).signum(), winding(&unflipped).signum());
+        }
+    }
+}
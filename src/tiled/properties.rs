@@ -0,0 +1,537 @@
+//! Builds reflected components from Tiled custom properties.
+//!
+//! An object or tile whose class names a registered component gets that component,
+//! with its properties as the fields. Each property of a custom class type that isn't a field
+//! of that component becomes a component of its own. Class names are matched against full type paths
+//! (e.g. `my_game::Health`) and then unambiguous short names (`Health`).
+//!
+//! Nested classes fill struct fields, properties named `0`, `1`, ... fill tuple struct fields,
+//! and string properties select unit enum variants. Fields without a property keep their
+//! `Default` value if the type reflects `Default`.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use bevy_ecs::entity::Entity;
+use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+use bevy_ecs::world::World;
+use bevy_reflect::std_traits::ReflectDefault;
+use bevy_reflect::{
+    DynamicEnum, DynamicStruct, DynamicTupleStruct, DynamicVariant, PartialReflect, ReflectFromReflect,
+    TypeInfo, TypeRegistration, TypeRegistry,
+};
+use core::any::TypeId;
+use core::fmt;
+use num_traits::Float;
+use tiled::{Properties, PropertyValue};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyError {
+    /// No registered type has this path or short name.
+    UnknownType(String),
+    /// The type exists but isn't a `#[reflect(Component)]`.
+    NotAComponent(String),
+    UnknownField { ty: String, field: String },
+    /// The property's Tiled type can't be converted to the field's type.
+    Mismatch { ty: String, expected: String, found: &'static str },
+    UnknownVariant { ty: String, variant: String },
+    /// The value couldn't be turned into the type, usually because fields are missing
+    /// and it doesn't reflect `Default`.
+    Incomplete(String),
+}
+
+impl fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyError::UnknownType(ty) => write!(f, "unknown type `{ty}`, is it registered?"),
+            PropertyError::NotAComponent(ty) => write!(f, "`{ty}` is not a component, add #[reflect(Component)]"),
+            PropertyError::UnknownField { ty, field } => write!(f, "`{ty}` has no field `{field}`"),
+            PropertyError::Mismatch { ty, expected, found } => {
+                write!(f, "`{ty}` expected {expected}, found a {found} property")
+            }
+            PropertyError::UnknownVariant { ty, variant } => write!(f, "`{ty}` has no unit variant `{variant}`"),
+            PropertyError::Incomplete(ty) => {
+                write!(f, "can't build `{ty}`, set every field or reflect Default")
+            }
+        }
+    }
+}
+
+/// Inserts the components described by an object's or tile's `class` and `properties` into `entity`.
+///
+/// Class-valued properties become components of their own, unless they fill a field of the class.
+/// A class that isn't a registered type is only an error if there are other properties to fill it with,
+/// as classes are also used as plain tags. Every property is tried, so all errors are reported and not
+/// only the first.
+pub fn insert_properties(
+    world: &mut World,
+    entity: Entity,
+    class: &str,
+    properties: &Properties,
+) -> Result<(), alloc::vec::Vec<PropertyError>> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let mut components = alloc::vec::Vec::new();
+    let mut errors = alloc::vec::Vec::new();
+
+    let class_type = resolve(&registry, class).ok();
+    let mut fields = Properties::default();
+    for (name, value) in properties {
+        match value {
+            PropertyValue::ClassValue { property_type, properties }
+                if !class_type.is_some_and(|registration| has_field(registration, name)) =>
+            {
+                match component_from_properties(&registry, property_type, properties) {
+                    Ok(component) => components.push(component),
+                    Err(e) => errors.push(e),
+                }
+            }
+            _ => {
+                fields.insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    if !class.is_empty() && (class_type.is_some() || !fields.is_empty()) {
+        match component_from_properties(&registry, class, &fields) {
+            Ok(component) => components.push(component),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let mut entity_mut = world.entity_mut(entity);
+    for (reflect, value) in components {
+        reflect.insert(&mut entity_mut, value.as_partial_reflect(), &registry);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Whether `name` is a field of the type, so a property with that name fills it.
+fn has_field(registration: &TypeRegistration, name: &str) -> bool {
+    match registration.type_info() {
+        TypeInfo::Struct(s) => s.field(name).is_some(),
+        TypeInfo::TupleStruct(s) => name.parse::<usize>().is_ok_and(|i| i < s.field_len()),
+        _ => false,
+    }
+}
+
+fn component_from_properties<'a>(
+    registry: &'a TypeRegistry,
+    class: &str,
+    properties: &Properties,
+) -> Result<(&'a ReflectComponent, Box<dyn PartialReflect>), PropertyError> {
+    let registration = resolve(registry, class)?;
+    let reflect = registration
+        .data::<ReflectComponent>()
+        .ok_or_else(|| PropertyError::NotAComponent(class.to_string()))?;
+    let value = from_class(registry, registration, properties)?;
+    Ok((reflect, value))
+}
+
+fn resolve<'a>(registry: &'a TypeRegistry, name: &str) -> Result<&'a TypeRegistration, PropertyError> {
+    registry
+        .get_with_type_path(name)
+        .or_else(|| registry.get_with_short_type_path(name))
+        .ok_or_else(|| PropertyError::UnknownType(name.to_string()))
+}
+
+/// Builds a concrete value of `registration`'s type from the properties of a class.
+fn from_class(
+    registry: &TypeRegistry,
+    registration: &TypeRegistration,
+    properties: &Properties,
+) -> Result<Box<dyn PartialReflect>, PropertyError> {
+    let info = registration.type_info();
+    let ty = || info.type_path().to_string();
+    let field_type = |type_id: TypeId| registry.get(type_id).ok_or_else(|| PropertyError::UnknownType(ty()));
+
+    let patch: Box<dyn PartialReflect> = match info {
+        TypeInfo::Struct(s) => {
+            let mut dynamic = DynamicStruct::default();
+            for (name, value) in properties {
+                let field = s.field(name).ok_or_else(|| PropertyError::UnknownField {
+                    ty: ty(),
+                    field: name.clone(),
+                })?;
+                dynamic.insert_boxed(name, from_property(registry, field_type(field.type_id())?, value)?);
+            }
+            Box::new(dynamic)
+        }
+        TypeInfo::TupleStruct(s) => {
+            let mut dynamic = DynamicTupleStruct::default();
+            for i in 0..s.field_len() {
+                // tuple fields can only be given in order, from the first
+                let Some(value) = properties.get(&i.to_string()) else {
+                    break;
+                };
+                let field = s.field_at(i).unwrap();
+                dynamic.insert_boxed(from_property(registry, field_type(field.type_id())?, value)?);
+            }
+            if let Some(name) = properties.keys().find(|k| k.parse::<usize>().map_or(true, |i| i >= dynamic.field_len())) {
+                return Err(PropertyError::UnknownField { ty: ty(), field: name.clone() });
+            }
+            Box::new(dynamic)
+        }
+        _ => {
+            return Err(PropertyError::Mismatch {
+                ty: ty(),
+                expected: "a struct".into(),
+                found: "class",
+            })
+        }
+    };
+
+    concrete(registration, patch)
+}
+
+/// Turns a dynamic value into the real type, starting from its `Default` if it has one.
+fn concrete(
+    registration: &TypeRegistration,
+    patch: Box<dyn PartialReflect>,
+) -> Result<Box<dyn PartialReflect>, PropertyError> {
+    let ty = registration.type_info().type_path();
+    if let Some(default) = registration.data::<ReflectDefault>() {
+        let mut value = default.default();
+        value
+            .try_apply(patch.as_ref())
+            .map_err(|_| PropertyError::Incomplete(ty.to_string()))?;
+        return Ok(value.into_partial_reflect());
+    }
+    registration
+        .data::<ReflectFromReflect>()
+        .and_then(|from_reflect| from_reflect.from_reflect(patch.as_ref()))
+        .map(|value| value.into_partial_reflect())
+        .ok_or_else(|| PropertyError::Incomplete(ty.to_string()))
+}
+
+/// Converts a single property to the type of the field it fills.
+fn from_property(
+    registry: &TypeRegistry,
+    registration: &TypeRegistration,
+    value: &PropertyValue,
+) -> Result<Box<dyn PartialReflect>, PropertyError> {
+    let info = registration.type_info();
+    let mismatch = |expected: &str| PropertyError::Mismatch {
+        ty: info.type_path().to_string(),
+        expected: expected.to_string(),
+        found: kind(value),
+    };
+
+    if let PropertyValue::ClassValue { properties, .. } = value {
+        return from_class(registry, registration, properties);
+    }
+
+    if let TypeInfo::Enum(e) = info {
+        let PropertyValue::StringValue(variant) = value else {
+            return Err(mismatch("the name of a variant"));
+        };
+        if e.variant(variant).is_none() {
+            return Err(PropertyError::UnknownVariant {
+                ty: info.type_path().to_string(),
+                variant: variant.clone(),
+            });
+        }
+        let dynamic = DynamicEnum::new(variant.clone(), DynamicVariant::Unit);
+        return concrete(registration, Box::new(dynamic));
+    }
+
+    let type_id = info.type_id();
+    macro_rules! float {
+        ($($t:ty),*) => {
+            $(
+                if type_id == TypeId::of::<$t>() {
+                    return match *value {
+                        PropertyValue::IntValue(v) => Ok(Box::new(v as $t)),
+                        PropertyValue::FloatValue(v) => Ok(Box::new(v as $t)),
+                        _ => Err(mismatch("a number")),
+                    };
+                }
+            )*
+        };
+    }
+    float!(f32, f64);
+
+    // integers must fit exactly, rather than wrapping, truncating or saturating
+    macro_rules! int {
+        ($($t:ty),*) => {
+            $(
+                if type_id == TypeId::of::<$t>() {
+                    let whole = |v: i64| <$t>::try_from(v).ok();
+                    let converted = match *value {
+                        PropertyValue::IntValue(v) => whole(v.into()),
+                        PropertyValue::ObjectValue(v) => whole(v.into()),
+                        PropertyValue::FloatValue(v)
+                            if v.fract() == 0.0 && v >= i64::MIN as f32 && v < i64::MAX as f32 =>
+                        {
+                            whole(v as i64)
+                        }
+                        _ => None,
+                    };
+                    return converted
+                        .map(|v| Box::new(v) as Box<dyn PartialReflect>)
+                        .ok_or_else(|| {
+                            mismatch(&format!("a whole number from {} to {}", <$t>::MIN, <$t>::MAX))
+                        });
+                }
+            )*
+        };
+    }
+    int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+    if type_id == TypeId::of::<bool>() {
+        return match *value {
+            PropertyValue::BoolValue(v) => Ok(Box::new(v)),
+            _ => Err(mismatch("a bool")),
+        };
+    }
+    if type_id == TypeId::of::<String>() {
+        return match value {
+            PropertyValue::StringValue(v) | PropertyValue::FileValue(v) => Ok(Box::new(v.clone())),
+            _ => Err(mismatch("a string")),
+        };
+    }
+
+    Err(mismatch(&format!("a class of type {}", info.type_path())))
+}
+
+fn kind(value: &PropertyValue) -> &'static str {
+    match value {
+        PropertyValue::BoolValue(_) => "bool",
+        PropertyValue::FloatValue(_) => "float",
+        PropertyValue::IntValue(_) => "int",
+        PropertyValue::ColorValue(_) => "color",
+        PropertyValue::StringValue(_) => "string",
+        PropertyValue::FileValue(_) => "file",
+        PropertyValue::ObjectValue(_) => "object",
+        PropertyValue::ClassValue { .. } => "class",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tiled::properties::{insert_properties, PropertyError};
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+    use bevy_reflect::std_traits::ReflectDefault;
+    use bevy_reflect::{Reflect, TypePath};
+    use tiled::{Properties, PropertyValue};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Spawner {
+        interval: f32,
+        count: u8,
+        kind: Kind,
+        offset: Offset,
+    }
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    #[reflect(Default)]
+    enum Kind {
+        #[default]
+        Coin,
+        Gem,
+    }
+
+    #[derive(Reflect, Default, Debug, PartialEq)]
+    #[reflect(Default)]
+    struct Offset {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Label(String, u16);
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        {
+            let mut registry = world.resource::<AppTypeRegistry>().write();
+            registry.register::<Spawner>();
+            registry.register::<Kind>();
+            registry.register::<Offset>();
+            registry.register::<Label>();
+        }
+        world
+    }
+
+    fn props<const N: usize>(properties: [(&str, PropertyValue); N]) -> Properties {
+        properties.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+    }
+
+    fn class<const N: usize>(property_type: &str, properties: [(&str, PropertyValue); N]) -> PropertyValue {
+        PropertyValue::ClassValue {
+            property_type: property_type.to_string(),
+            properties: props(properties),
+        }
+    }
+
+    fn label(properties: [(&str, PropertyValue); 2]) -> Properties {
+        props([("label", class("Label", properties))])
+    }
+
+    fn insert(world: &mut World, class: &str, properties: Properties) -> (Entity, Result<(), Vec<PropertyError>>) {
+        let entity = world.spawn_empty().id();
+        let result = insert_properties(world, entity, class, &properties);
+        (entity, result)
+    }
+
+    #[test]
+    fn test_class_and_component_properties() {
+        let mut world = world();
+        let (entity, result) = insert(
+            &mut world,
+            "Spawner",
+            props([
+                ("interval", PropertyValue::FloatValue(1.5)),
+                ("count", PropertyValue::IntValue(3)),
+                ("kind", PropertyValue::StringValue("Gem".into())),
+                ("offset", class("Offset", [("x", PropertyValue::IntValue(-2))])),
+                (
+                    "label",
+                    class("Label", [("0", PropertyValue::StringValue("door".into())), ("1", PropertyValue::IntValue(7))]),
+                ),
+            ]),
+        );
+        assert_eq!(result, Ok(()));
+
+        let entity = world.entity(entity);
+        assert_eq!(
+            entity.get::<Spawner>(),
+            Some(&Spawner {
+                interval: 1.5,
+                count: 3,
+                kind: Kind::Gem,
+                offset: Offset { x: -2, y: 0 },
+            })
+        );
+        assert_eq!(entity.get::<Label>(), Some(&Label("door".into(), 7)));
+    }
+
+    #[test]
+    fn test_component_properties_without_class() {
+        let mut world = world();
+        let (entity, result) = insert(
+            &mut world,
+            "",
+            label([("0", PropertyValue::FileValue("a.txt".into())), ("1", PropertyValue::FloatValue(2.0))]),
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(world.get::<Label>(entity), Some(&Label("a.txt".into(), 2)));
+    }
+
+    #[test]
+    fn test_tag_class() {
+        let mut world = world();
+
+        // a class that isn't a type is fine as long as nothing has to fill it
+        let (entity, result) = insert(&mut world, "Enemy", Properties::default());
+        assert_eq!(result, Ok(()));
+        assert!(world.get::<Label>(entity).is_none());
+
+        let (entity, result) = insert(
+            &mut world,
+            "Enemy",
+            label([("0", PropertyValue::StringValue("boss".into())), ("1", PropertyValue::IntValue(1))]),
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!(world.get::<Label>(entity), Some(&Label("boss".into(), 1)));
+
+        let (_, result) = insert(&mut world, "Enemy", props([("speed", PropertyValue::FloatValue(1.0))]));
+        assert_eq!(result, Err(vec![PropertyError::UnknownType("Enemy".into())]));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut world = world();
+        let mut spawner_error = |property: (&str, PropertyValue)| {
+            let (entity, result) = insert(&mut world, "Spawner", props([property]));
+            assert!(world.get::<Spawner>(entity).is_none());
+            result.unwrap_err()
+        };
+
+        assert_eq!(
+            spawner_error(("count", PropertyValue::IntValue(256))),
+            [PropertyError::Mismatch {
+                ty: "u8".into(),
+                expected: "a whole number from 0 to 255".into(),
+                found: "int",
+            }]
+        );
+        assert_eq!(
+            spawner_error(("count", PropertyValue::FloatValue(2.5))),
+            [PropertyError::Mismatch {
+                ty: "u8".into(),
+                expected: "a whole number from 0 to 255".into(),
+                found: "float",
+            }]
+        );
+        assert_eq!(
+            spawner_error(("interval", PropertyValue::ObjectValue(4))),
+            [PropertyError::Mismatch {
+                ty: "f32".into(),
+                expected: "a number".into(),
+                found: "object",
+            }]
+        );
+        assert_eq!(
+            spawner_error(("kind", PropertyValue::StringValue("Ruby".into()))),
+            [PropertyError::UnknownVariant {
+                ty: Kind::type_path().into(),
+                variant: "Ruby".into(),
+            }]
+        );
+        assert_eq!(
+            spawner_error(("speed", PropertyValue::FloatValue(1.0))),
+            [PropertyError::UnknownField {
+                ty: Spawner::type_path().into(),
+                field: "speed".into(),
+            }]
+        );
+        assert_eq!(
+            spawner_error(("offset", class("Offset", [("z", PropertyValue::IntValue(1))]))),
+            [PropertyError::UnknownField {
+                ty: Offset::type_path().into(),
+                field: "z".into(),
+            }]
+        );
+
+        // tuple fields have to start from `0`, and `Label` has no `Default` to fill the rest
+        let (_, result) = insert(
+            &mut world,
+            "",
+            props([("label", class("Label", [("1", PropertyValue::IntValue(1))]))]),
+        );
+        assert_eq!(
+            result,
+            Err(vec![PropertyError::UnknownField {
+                ty: Label::type_path().into(),
+                field: "1".into(),
+            }])
+        );
+        let (_, result) = insert(
+            &mut world,
+            "",
+            props([("label", class("Label", [("0", PropertyValue::StringValue("door".into()))]))]),
+        );
+        assert_eq!(result, Err(vec![PropertyError::Incomplete(Label::type_path().into())]));
+
+        let (_, result) = insert(
+            &mut world,
+            "",
+            props([("missing", class("Missing", [("0", PropertyValue::IntValue(1))]))]),
+        );
+        assert_eq!(result, Err(vec![PropertyError::UnknownType("Missing".into())]));
+    }
+}
@@ -0,0 +1,280 @@
+//! Rail tracks drawn as Tiled polylines and polygons.
+//!
+//! Give a polyline or polygon object the class `TiledTrack` and it is turned into
+//! [`Segment`](crate::curve::Segment)s and [`Joint`]s instead of a collider. By default every edge is a straight line
+//! and corners are sharp. Corners can be rounded with the `corner_radius` property, or per vertex
+//! with `corner_radii` (e.g. `0, 20, 35`, missing entries use `corner_radius`). Alternatively,
+//! `curvature` gives each edge a curvature (e.g. `0, 0.02, -0.01`), bending it into an arc
+//! between its two vertices. Positive curvature turns left, like [`CurveBuilder::segment`].
+//!
+//! Track ends that meet, within a pixel, share a [`Joint`], including ends of different objects.
+//! Tracks leaving a joint in the same direction become the branches of a switch.
+
+use crate::builder::builders::{arc_curvature_length, line_to};
+use crate::builder::{CurveBuilder, CurveParent, GraphBuilder};
+use crate::curve::Joint;
+use alloc::string::String;
+use alloc::vec::Vec;
+use bevy_ecs::prelude::*;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_reflect::std_traits::ReflectDefault;
+use bevy_reflect::Reflect;
+use bevy_transform::prelude::Transform;
+use curve::arc::ArcSegment;
+use curve::traits::{CurveSegment, CurveType};
+use glam::Vec2;
+use num_traits::Float;
+
+/// Ends closer than this are joined.
+const MERGE_DISTANCE: f32 = 1.0;
+
+/// Marks a Tiled polyline or polygon as a track, see the [module docs](self).
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component, Default)]
+pub struct TiledTrack {
+    /// Radius of every rounded corner, `0` for sharp corners.
+    pub corner_radius: f32,
+    /// Comma-separated radius of each vertex's corner, overriding `corner_radius`.
+    pub corner_radii: String,
+    /// Comma-separated curvature of each edge. Corners aren't rounded if this is set.
+    pub curvature: String,
+    pub line_width: i32,
+}
+
+impl Default for TiledTrack {
+    fn default() -> Self {
+        Self {
+            corner_radius: 0.0,
+            corner_radii: String::new(),
+            curvature: String::new(),
+            line_width: 4,
+        }
+    }
+}
+
+impl TiledTrack {
+    /// The curves through `points`, back to the first point if `closed`.
+    pub fn curves(&self, points: &[Vec2], closed: bool) -> Result<Vec<CurveType>, String> {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| a.distance(*b) < MERGE_DISTANCE);
+        if closed && points.len() > 1 && points[0].distance(points[points.len() - 1]) < MERGE_DISTANCE {
+            points.pop();
+        }
+        if points.len() < 2 {
+            return Err("a track needs at least two points".into());
+        }
+
+        if self.curvature.trim().is_empty() {
+            let radii = parse_list(&self.corner_radii)?;
+            let radius = |i: usize| radii.get(i).copied().unwrap_or(self.corner_radius);
+            Ok(if closed {
+                // start halfway along the first edge so every vertex is a corner
+                let start = (points[0] + points[1]) / 2.0;
+                let corners: Vec<(Vec2, f32)> = (1..=points.len())
+                    .map(|i| (points[i % points.len()], radius(i % points.len())))
+                    .collect();
+                rounded(start, &corners, start)
+            } else {
+                let corners: Vec<(Vec2, f32)> = (1..points.len() - 1).map(|i| (points[i], radius(i))).collect();
+                rounded(points[0], &corners, points[points.len() - 1])
+            })
+        } else {
+            let curvatures = parse_list(&self.curvature)?;
+            if closed {
+                points.push(points[0]);
+            }
+            bent(&points, &curvatures)
+        }
+    }
+}
+
+fn parse_list(list: &str) -> Result<Vec<f32>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| alloc::format!("`{s}` is not a number")))
+        .collect()
+}
+
+/// Straight lines from `start` through `corners` to `end`, with each corner rounded off by an arc
+/// of its radius. Radii too big for the edges next to them are shrunk to fit.
+fn rounded(start: Vec2, corners: &[(Vec2, f32)], end: Vec2) -> Vec<CurveType> {
+    let points: Vec<Vec2> = core::iter::once(start)
+        .chain(corners.iter().map(|&(p, _)| p))
+        .chain(core::iter::once(end))
+        .collect();
+
+    let mut builder = CurveBuilder::new(start, (points[1] - start).normalize());
+    for (i, &(corner, radius)) in corners.iter().enumerate() {
+        let (prev, next) = (points[i], points[i + 2]);
+        let dir_in = (corner - prev).normalize();
+        let dir_out = (next - corner).normalize();
+        // clockwise on screen is positive, as y points down
+        let angle = dir_in.perp_dot(dir_out).atan2(dir_in.dot(dir_out));
+
+        if radius <= 0.0 || angle.abs() < 1e-3 {
+            builder = line_until(builder, corner);
+            continue;
+        }
+
+        // the edges at either end of the path are not shared with another corner
+        let room_in = corner.distance(prev) / if i == 0 { 1.0 } else { 2.0 };
+        let room_out = corner.distance(next) / if i == corners.len() - 1 { 1.0 } else { 2.0 };
+        let half_tan = (angle.abs() / 2.0).tan();
+        let tangent = (radius * half_tan).min(room_in).min(room_out);
+        let radius = tangent / half_tan;
+
+        builder = line_until(builder, corner - dir_in * tangent)
+            .push(arc_curvature_length(radius * angle.abs(), -angle.signum() / radius));
+    }
+    line_until(builder, end).into_curves()
+}
+
+fn line_until(builder: CurveBuilder, target: Vec2) -> CurveBuilder {
+    if builder.position().distance(target) < 1e-3 {
+        builder
+    } else {
+        builder.push(line_to(target))
+    }
+}
+
+/// An arc, or a line where the curvature is `0` or missing, between each pair of consecutive points.
+/// Fails if an edge is longer than the diameter of its curvature's circle.
+fn bent(points: &[Vec2], curvatures: &[f32]) -> Result<Vec<CurveType>, String> {
+    let mut builder = CurveBuilder::new(points[0], (points[1] - points[0]).normalize());
+    for (i, pair) in points.windows(2).enumerate() {
+        let (start, end) = (pair[0], pair[1]);
+        let chord = end - start;
+        let curvature = curvatures.get(i).copied().unwrap_or(0.0);
+
+        if curvature == 0.0 {
+            builder = builder.push(line_to(end));
+            continue;
+        }
+        if chord.length() * curvature.abs() > 2.0 {
+            return Err(alloc::format!(
+                "edge {i} is {} long, more than the diameter {} of its curvature {curvature}",
+                chord.length(),
+                2.0 / curvature.abs(),
+            ));
+        }
+
+        let angle = 2.0 * (chord.length() * curvature.abs() / 2.0).asin();
+        builder = builder.push(move |pos: &mut Vec2, dir: &mut Vec2| {
+            // start off to the side of the chord the arc bulges out to
+            let start_dir = Vec2::from_angle(curvature.signum() * angle / 2.0).rotate(chord.normalize());
+            let arc = ArcSegment::from_pos_dir_curvature_length(start, start_dir, curvature, angle / curvature.abs());
+            *pos = end;
+            *dir = arc.dir(1.0).normalize_or_zero();
+            CurveType::Arc(arc)
+        });
+    }
+    Ok(builder.into_curves())
+}
+
+/// Spawns `curves`, each with its line width, as one track graph under a new [`CurveParent`].
+/// Ends within [`MERGE_DISTANCE`] of each other share a [`Joint`], see [`GraphBuilder`].
+pub fn spawn_track_graph(world: &mut World, curves: Vec<(CurveType, i32)>) -> Entity {
+    let parent = world.spawn((Name::new("Tracks"), Transform::default(), CurveParent)).id();
+    let graph = curves
+        .into_iter()
+        .fold(GraphBuilder::new(), |graph, (curve, line_width)| graph.push(curve, line_width));
+    graph.with_tolerance(MERGE_DISTANCE, 5f32.to_radians()).build_in(&mut world.commands(), parent);
+    world.flush();
+    parent
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tiled::tracks::TiledTrack;
+    use alloc::string::ToString;
+    use core::f32::consts::{FRAC_PI_2, PI};
+    use curve::traits::{CurveSegment, CurveType};
+    use glam::Vec2;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{a} != {b}");
+    }
+
+    /// Each curve starts where the last one ended, heading the same way.
+    fn assert_smooth(curves: &[CurveType]) {
+        for pair in curves.windows(2) {
+            assert_near(pair[0].position(1.0), pair[1].position(0.0));
+            assert!(pair[0].dir(1.0).dot(*pair[1].dir(0.0)) > 0.999, "{pair:?} has a kink");
+        }
+    }
+
+    fn length(curves: &[CurveType]) -> f32 {
+        curves.iter().map(CurveType::length).sum()
+    }
+
+    #[test]
+    fn test_corner_radius_clamped() {
+        let points = [Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 100.0)];
+        let track = TiledTrack { corner_radius: 50.0, ..Default::default() };
+
+        // the first edge only has room for a radius of 10
+        let curves = track.curves(&points, false).unwrap();
+        assert_smooth(&curves);
+        let [CurveType::Arc(arc), CurveType::Line(line)] = curves.as_slice() else {
+            panic!("expected an arc and a line, found {curves:?}");
+        };
+        assert!((arc.radius - 10.0).abs() < 1e-3);
+        assert_near(arc.position(0.0), Vec2::ZERO);
+        assert_near(arc.position(1.0), Vec2::new(10.0, 10.0));
+        assert_eq!(line.end, Vec2::new(10.0, 100.0));
+
+        let track = TiledTrack { corner_radius: 50.0, corner_radii: "0, 0".to_string(), ..Default::default() };
+        let curves = track.curves(&points, false).unwrap();
+        assert!(matches!(curves.as_slice(), [CurveType::Line(_), CurveType::Line(_)]));
+        assert!((length(&curves) - 110.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_closed_starts_mid_edge() {
+        let square = [Vec2::ZERO, Vec2::new(20.0, 0.0), Vec2::new(20.0, 20.0), Vec2::new(0.0, 20.0)];
+        let track = TiledTrack { corner_radius: 5.0, ..Default::default() };
+
+        let curves = track.curves(&square, true).unwrap();
+        assert_smooth(&curves);
+        // a line before and after each of the four corners, the first and last lines meeting mid-edge
+        assert_eq!(curves.len(), 9);
+        assert_eq!(curves.iter().filter(|c| matches!(c, CurveType::Arc(_))).count(), 4);
+        assert_near(curves[0].position(0.0), Vec2::new(10.0, 0.0));
+        assert_near(curves[8].position(1.0), Vec2::new(10.0, 0.0));
+        assert!((length(&curves) - (40.0 + 4.0 * 5.0 * FRAC_PI_2)).abs() < 1e-2);
+
+        // repeating the first point, as some editors do, is the same polygon
+        let repeated = [&square[..], &[Vec2::ZERO]].concat();
+        assert_eq!(track.curves(&repeated, true).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn test_bent_arcs_end_on_vertices() {
+        let points = [Vec2::ZERO, Vec2::new(16.0, 0.0), Vec2::new(16.0, 16.0), Vec2::new(0.0, 16.0)];
+        let track = TiledTrack { curvature: "0.125, -0.05, 0".to_string(), ..Default::default() };
+
+        let curves = track.curves(&points, false).unwrap();
+        let [CurveType::Arc(half), CurveType::Arc(shallow), CurveType::Line(_)] = curves.as_slice() else {
+            panic!("expected two arcs and a line, found {curves:?}");
+        };
+        // a chord as long as the diameter is a half circle
+        assert!((half.length() - 8.0 * PI).abs() < 1e-3);
+        for (curve, (start, end)) in curves.iter().zip(points.iter().zip(&points[1..])) {
+            assert_near(curve.position(0.0), *start);
+            assert_near(curve.position(1.0), *end);
+        }
+        assert!((shallow.radius - 20.0).abs() < 1e-3);
+
+        // closed tracks bend the edge back to the start too
+        let track = TiledTrack { curvature: "0, 0, 0, 0.05".to_string(), ..Default::default() };
+        let curves = track.curves(&points, true).unwrap();
+        assert_eq!(curves.len(), 4);
+        assert_near(curves[3].position(0.0), points[3]);
+        assert_near(curves[3].position(1.0), points[0]);
+
+        let track = TiledTrack { curvature: "0.2".to_string(), ..Default::default() };
+        let error = track.curves(&points, false).unwrap_err();
+        assert!(error.starts_with("edge 0 is 16 long"), "{error}");
+    }
+}
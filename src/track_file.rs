@@ -0,0 +1,513 @@
+//! A text format for tracks, so they can be changed without recompiling.
+//!
+//! Statements are separated by `;` or new lines, and `#` starts a comment:
+//! ```text
+//! start 100 200 0      # position, and angle in degrees (counterclockwise, 0 is right)
+//! width 3              # line width
+//! line 50
+//! mark left            # save the cursor as `left`
+//! fork { arc r=50 rev=0.25; line 50 }
+//! arc len=100 k=0.02   # length and curvature, see `CurveBuilder::segment`
+//! fork left reversed { line 20 }
+//! join left            # end with a line back to `left`
+//! closed               # connect the end to the start
+//! ```
+//! `fork` builds a branch from the cursor, or from a saved one, and continues where it was.
+//! Positive revolutions and curvatures turn left.
+
+use crate::builder::builders::{arc, line};
+use crate::builder::{Cursor, CurveBuilder};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_ecs::entity::Entity;
+use bevy_ecs::prelude::Commands;
+use bevy_playdate::file::FileHandle;
+use bevy_playdate::fs::Location;
+use core::fmt;
+use glam::Vec2;
+use no_std_io2::io::{self, Read};
+use num_traits::Float;
+
+/// A track read from the text format, ready to spawn.
+pub struct TrackFile {
+    pub builder: CurveBuilder,
+    pub line_width: i32,
+    pub join_ends: bool,
+}
+
+impl TrackFile {
+    pub fn parse(source: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(source);
+        let mut parser = Parser { tokens, next: 0 };
+        let statements = parser.block(false)?;
+
+        let mut track = TrackFile {
+            builder: CurveBuilder::new(Vec2::ZERO, Vec2::X),
+            line_width: 4,
+            join_ends: false,
+        };
+        let mut marks = BTreeMap::new();
+        for (i, statement) in statements.into_iter().enumerate() {
+            match statement.kind {
+                Kind::Start { pos, angle } if i == 0 => track.builder = CurveBuilder::new(pos, direction(angle)),
+                Kind::Start { .. } => return Err(statement.error("`start` must come first")),
+                Kind::Width(width) => track.line_width = width,
+                Kind::Closed => track.join_ends = true,
+                _ => track.builder = apply(track.builder, statement, &mut marks)?,
+            }
+        }
+        Ok(track)
+    }
+
+    /// Reads and parses a track file from the data folder, or else the bundle.
+    pub fn load(path: &str) -> Result<Self, LoadError> {
+        let mut source = String::new();
+        FileHandle::open(path, Location::Any.read_options())
+            .and_then(|mut file| file.read_to_string(&mut source))
+            .map_err(LoadError::Io)?;
+        Self::parse(&source).map_err(LoadError::Parse)
+    }
+
+    pub fn spawn(self, commands: &mut Commands) -> (Vec<Entity>, Vec<Entity>) {
+        self.builder.build(commands, self.line_width, self.join_ends)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{e}"),
+            LoadError::Parse(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// Direction of an angle in degrees, counterclockwise on screen.
+fn direction(degrees: f32) -> Vec2 {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Vec2::new(cos, -sin)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Open,
+    Close,
+    /// `;` or a new line.
+    End,
+}
+
+struct Spanned<T> {
+    value: T,
+    line: usize,
+    column: usize,
+}
+
+impl<T> Spanned<T> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Spanned<Token<'_>>> {
+    let mut tokens = Vec::new();
+    for (line, text) in source.lines().enumerate() {
+        let text = text.split('#').next().unwrap_or_default();
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let column = text[..start].chars().count() + 1;
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                '{' => Token::Open,
+                '}' => Token::Close,
+                ';' => Token::End,
+                _ => {
+                    let mut end = text.len();
+                    while let Some(&(i, c)) = chars.peek() {
+                        if c.is_whitespace() || matches!(c, '{' | '}' | ';') {
+                            end = i;
+                            break;
+                        }
+                        chars.next();
+                    }
+                    Token::Word(&text[start..end])
+                }
+            };
+            tokens.push(Spanned { value: token, line: line + 1, column });
+        }
+        tokens.push(Spanned {
+            value: Token::End,
+            line: line + 1,
+            column: text.chars().count() + 1,
+        });
+    }
+    tokens
+}
+
+enum Kind {
+    Start { pos: Vec2, angle: f32 },
+    Width(i32),
+    Closed,
+    Line(f32),
+    Arc { radius: f32, revolutions: f32 },
+    Segment { length: f32, curvature: f32 },
+    Mark(String),
+    Fork { from: Option<(String, bool)>, body: Vec<Statement> },
+    Join(String),
+}
+
+struct Statement {
+    kind: Kind,
+    line: usize,
+    column: usize,
+}
+
+impl Statement {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Spanned<Token<'a>>>,
+    next: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Spanned<Token<'a>>> {
+        self.tokens.get(self.next)
+    }
+
+    /// Statements until the end, or until `}` if `nested`.
+    fn block(&mut self, nested: bool) -> Result<Vec<Statement>, ParseError> {
+        let mut statements = Vec::new();
+        loop {
+            let Some(token) = self.tokens.get(self.next) else {
+                if nested {
+                    let last = self.tokens.last();
+                    return Err(ParseError {
+                        line: last.map_or(1, |t| t.line),
+                        column: last.map_or(1, |t| t.column),
+                        message: "missing `}`".into(),
+                    });
+                }
+                return Ok(statements);
+            };
+            self.next += 1;
+            match token.value {
+                Token::End => {}
+                Token::Close if nested => return Ok(statements),
+                Token::Close => return Err(token.error("unexpected `}`")),
+                Token::Open => return Err(token.error("unexpected `{`")),
+                Token::Word(word) => {
+                    let (line, column) = (token.line, token.column);
+                    let kind = self.statement(word, line, column)?;
+                    statements.push(Statement { kind, line, column });
+                }
+            }
+        }
+    }
+
+    /// The words up to the end of the statement or a `{`.
+    fn args(&mut self) -> Vec<Spanned<&'a str>> {
+        let mut args = Vec::new();
+        while let Some(Spanned {
+            value: Token::Word(word),
+            line,
+            column,
+        }) = self.peek()
+        {
+            args.push(Spanned {
+                value: *word,
+                line: *line,
+                column: *column,
+            });
+            self.next += 1;
+        }
+        args
+    }
+
+    fn statement(&mut self, name: &str, line: usize, column: usize) -> Result<Kind, ParseError> {
+        let at = Spanned { value: name, line, column };
+        let args = self.args();
+        let count = |n: usize| {
+            if args.len() == n {
+                Ok(())
+            } else {
+                Err(at.error(format!("`{name}` takes {n} arguments, found {}", args.len())))
+            }
+        };
+
+        let kind = match name {
+            "start" => {
+                if args.len() != 2 && args.len() != 3 {
+                    return Err(at.error(format!("`start` takes x, y and an optional angle, found {} arguments", args.len())));
+                }
+                Kind::Start {
+                    pos: Vec2::new(number(&args[0])?, number(&args[1])?),
+                    angle: args.get(2).map_or(Ok(0.0), number)?,
+                }
+            }
+            "width" => {
+                count(1)?;
+                let width = &args[0];
+                Kind::Width(
+                    width
+                        .value
+                        .parse()
+                        .map_err(|_| width.error(format!("`{}` is not a whole number", width.value)))?,
+                )
+            }
+            "closed" => {
+                count(0)?;
+                Kind::Closed
+            }
+            "line" => {
+                count(1)?;
+                Kind::Line(number(&args[0])?)
+            }
+            "arc" => {
+                count(2)?;
+                let mut keys = Keys::new(&at, &args)?;
+                match (keys.take("r"), keys.take("rev"), keys.take("len"), keys.take("k")) {
+                    (Some(radius), Some(revolutions), None, None) if radius > 0.0 => Kind::Arc { radius, revolutions },
+                    (Some(_), Some(_), None, None) => return Err(at.error("`r` must be positive")),
+                    (None, None, Some(length), Some(curvature)) => Kind::Segment { length, curvature },
+                    _ => return Err(at.error("`arc` takes either `r=` and `rev=`, or `len=` and `k=`")),
+                }
+            }
+            "mark" => {
+                count(1)?;
+                Kind::Mark(args[0].value.to_string())
+            }
+            "join" => {
+                count(1)?;
+                Kind::Join(args[0].value.to_string())
+            }
+            "fork" => {
+                let from = match args.as_slice() {
+                    [] => None,
+                    [mark] => Some((mark.value.to_string(), false)),
+                    [mark, reversed] if reversed.value == "reversed" => Some((mark.value.to_string(), true)),
+                    _ => return Err(at.error("expected `fork [mark [reversed]] { ... }`")),
+                };
+                match self.peek() {
+                    Some(Spanned { value: Token::Open, .. }) => self.next += 1,
+                    _ => return Err(at.error("expected `{` after `fork`")),
+                }
+                Kind::Fork {
+                    from,
+                    body: self.block(true)?,
+                }
+            }
+            _ => return Err(at.error(format!("unknown statement `{name}`"))),
+        };
+
+        match self.peek() {
+            None | Some(Spanned { value: Token::End | Token::Close, .. }) => Ok(kind),
+            Some(token) => Err(token.error("expected `;` or a new line")),
+        }
+    }
+}
+
+fn number(arg: &Spanned<&str>) -> Result<f32, ParseError> {
+    arg.value
+        .parse()
+        .map_err(|_| arg.error(format!("`{}` is not a number", arg.value)))
+}
+
+/// `key=value` arguments.
+struct Keys<'a> {
+    values: BTreeMap<&'a str, f32>,
+}
+
+impl<'a> Keys<'a> {
+    fn new(at: &Spanned<&str>, args: &[Spanned<&'a str>]) -> Result<Self, ParseError> {
+        let mut values = BTreeMap::new();
+        for arg in args {
+            let (key, value) = arg
+                .value
+                .split_once('=')
+                .ok_or_else(|| arg.error(format!("expected `key=value`, found `{}`", arg.value)))?;
+            let value = number(&Spanned {
+                value,
+                line: arg.line,
+                column: arg.column + key.chars().count() + 1,
+            })?;
+            if values.insert(key, value).is_some() {
+                return Err(at.error(format!("`{key}` is given twice")));
+            }
+        }
+        Ok(Keys { values })
+    }
+
+    fn take(&mut self, key: &str) -> Option<f32> {
+        self.values.remove(key)
+    }
+}
+
+/// Adds a statement that builds track to `builder`.
+fn apply(
+    builder: CurveBuilder,
+    statement: Statement,
+    marks: &mut BTreeMap<String, Cursor>,
+) -> Result<CurveBuilder, ParseError> {
+    let mark = |name: &str, marks: &BTreeMap<String, Cursor>| {
+        marks
+            .get(name)
+            .copied()
+            .ok_or_else(|| statement.error(format!("no mark named `{name}`")))
+    };
+
+    Ok(match statement.kind {
+        Kind::Line(length) => builder.push(line(length)),
+        Kind::Arc { radius, revolutions } => builder.push(arc(radius, revolutions)),
+        Kind::Segment { length, curvature } => builder.segment(length, curvature),
+        Kind::Mark(ref name) => {
+            marks.insert(name.clone(), builder.cursor());
+            builder
+        }
+        Kind::Join(ref name) => builder.join(mark(name, marks)?),
+        Kind::Fork { ref from, .. } => {
+            let cursor = match from {
+                None => builder.cursor(),
+                Some((name, false)) => mark(name, marks)?,
+                Some((name, true)) => mark(name, marks)?.reversed(),
+            };
+            let Kind::Fork { body, .. } = statement.kind else {
+                unreachable!()
+            };
+            let mut error = None;
+            let builder = builder.branch_from(cursor, |mut branch| {
+                for statement in body {
+                    match statement.kind {
+                        Kind::Start { .. } | Kind::Width(_) | Kind::Closed => {
+                            error = Some(statement.error("only allowed outside of `fork`"));
+                            break;
+                        }
+                        _ => match apply(branch, statement, marks) {
+                            Ok(b) => branch = b,
+                            Err(e) => {
+                                error = Some(e);
+                                return CurveBuilder::at(cursor);
+                            }
+                        },
+                    }
+                }
+                branch
+            });
+            if let Some(e) = error {
+                return Err(e);
+            }
+            builder
+        }
+        Kind::Start { .. } | Kind::Width(_) | Kind::Closed => return Err(statement.error("only allowed at the top level")),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::track_file::{ParseError, TrackFile};
+    use curve::traits::CurveSegment;
+    use glam::Vec2;
+
+    fn error(source: &str) -> ParseError {
+        match TrackFile::parse(source) {
+            Ok(_) => panic!("parsed {source:?}"),
+            Err(e) => e,
+        }
+    }
+
+    fn at(line: usize, column: usize, message: &str) -> ParseError {
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_3way() {
+        let track = TrackFile::parse(include_str!("../assets/3way.track")).unwrap();
+        assert_eq!(track.line_width, 3);
+        assert!(!track.join_ends);
+
+        // 3 lines of main track, then the arc and line of the first fork and the arc of the second
+        let curves = track.builder.into_curves();
+        assert_eq!(curves.len(), 6);
+        assert!((curves[0].position(0.0) - Vec2::new(100.0, 350.0)).length() < 1e-3);
+        assert!((curves[2].position(1.0) - Vec2::new(300.0, 350.0)).length() < 1e-3);
+        // both forks end at the top
+        assert!((curves[4].position(1.0) - Vec2::new(200.0, 250.0)).length() < 1e-3);
+        assert!((curves[5].position(1.0) - Vec2::new(200.0, 300.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_fork() {
+        let track = TrackFile::parse(
+            "start 0 0; line 50; mark middle; line 50\n\
+             fork middle { line 10 }\n\
+             fork middle reversed { line 10 }\n\
+             fork { line 10 }",
+        )
+        .unwrap();
+
+        let curves = track.builder.into_curves();
+        let ends = [
+            (Vec2::new(50.0, 0.0), Vec2::new(60.0, 0.0)),
+            (Vec2::new(50.0, 0.0), Vec2::new(40.0, 0.0)),
+            (Vec2::new(100.0, 0.0), Vec2::new(110.0, 0.0)),
+        ];
+        assert_eq!(curves.len(), 5);
+        for (curve, (start, end)) in curves[2..].iter().zip(ends) {
+            assert!((curve.position(0.0) - start).length() < 1e-3);
+            assert!((curve.position(1.0) - end).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_closed_fork() {
+        let track = TrackFile::parse("line 50\nfork { line 10 }\nclosed").unwrap();
+        assert!(track.join_ends);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error("line 50\nwiggle 3"), at(2, 1, "unknown statement `wiggle`"));
+        assert_eq!(error("fork {\n  line 50\n"), at(2, 10, "missing `}`"));
+        assert_eq!(error("line 50\narc r=5x rev=0.25"), at(2, 7, "`5x` is not a number"));
+        assert_eq!(error("line 50\nstart 0 0"), at(2, 1, "`start` must come first"));
+        assert_eq!(error("join nowhere"), at(1, 1, "no mark named `nowhere`"));
+        assert_eq!(error("fork {\n  width 3\n}"), at(2, 3, "only allowed outside of `fork`"));
+        assert_eq!(error("width 3.7"), at(1, 7, "`3.7` is not a whole number"));
+    }
+}
@@ -0,0 +1,395 @@
+//! Checks that track graphs are well formed.
+//!
+//! [`Joint::enter`] and the dot physics `unwrap` their lookups, so a broken graph otherwise
+//! only shows up as a panic once a dot reaches the broken part. [`validate_tracks`] runs the
+//! checks whenever segments or joints are added or changed and logs what is wrong.
+
+use crate::curve::{leaving, Joint, Segment};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bevy_ecs::name::NameOrEntity;
+use bevy_ecs::prelude::*;
+use bevy_playdate::error;
+use core::fmt;
+use curve::traits::CurveSegment;
+use num_traits::Float;
+
+/// How far the ends of the segments at a joint may be from each other.
+pub const POSITION_TOLERANCE: f32 = 1.0;
+/// How far apart, in degrees, the tangents of a [`JointConnection`](crate::curve::JointConnection) may be.
+pub const ANGLE_TOLERANCE: f32 = 5.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GraphError {
+    /// `field` of `entity` refers to an entity that is not a [`Segment`] or [`Joint`] (as expected).
+    Missing {
+        entity: String,
+        field: &'static str,
+        missing: Entity,
+    },
+    /// The joint at an end of a segment doesn't connect back to that end.
+    NotConnected { segment: String, joint: String, t: f32 },
+    /// A joint connects to a `t` other than `0` or `1`, or to an end of a segment
+    /// that belongs to another joint.
+    WrongEnd { joint: String, segment: String, t: f32 },
+    /// The end of a segment is too far from the joint, i.e. from the first end connected to it.
+    Misplaced {
+        joint: String,
+        segment: String,
+        t: f32,
+        distance: f32,
+    },
+    EmptyConnection { joint: String, index: usize },
+    /// Two segments in one connection leave the joint in different directions.
+    Tangents {
+        joint: String,
+        a: String,
+        b: String,
+        degrees: f32,
+    },
+    /// The segments of a connection aren't sorted from the sharpest right turn to the sharpest left turn.
+    CurvatureOrder { joint: String, a: String, b: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Missing { entity, field, missing } => {
+                write!(f, "{entity}: `{field}` refers to {missing}, which does not exist or is the wrong kind")
+            }
+            GraphError::NotConnected { segment, joint, t } => {
+                write!(f, "{segment}: {joint} at t = {t} has no connection back to it")
+            }
+            GraphError::WrongEnd { joint, segment, t } => {
+                write!(f, "{joint}: connects to {segment} at t = {t}, which is not one of its ends")
+            }
+            GraphError::Misplaced { joint, segment, t, distance } => {
+                write!(f, "{joint}: {segment} at t = {t} is {distance:.1} away from the joint")
+            }
+            GraphError::EmptyConnection { joint, index } => write!(f, "{joint}: connection {index} is empty"),
+            GraphError::Tangents { joint, a, b, degrees } => {
+                write!(f, "{joint}: {a} and {b} share a connection but are {degrees:.1} degrees apart")
+            }
+            GraphError::CurvatureOrder { joint, a, b } => {
+                write!(f, "{joint}: {a} turns further left than {b}, so it should come after it")
+            }
+        }
+    }
+}
+
+/// Checks every segment and joint, returning all the problems found.
+pub fn validate_graph(
+    segments: &Query<(Entity, &Segment)>,
+    joints: &Query<(Entity, &Joint)>,
+    names: &Query<NameOrEntity>,
+) -> Vec<GraphError> {
+    let name = |entity: Entity| names.get(entity).map_or_else(|_| entity.to_string(), |n| n.to_string());
+    let mut errors = Vec::new();
+
+    for (entity, segment) in segments {
+        for (field, joint, t) in [("start_joint", segment.start_joint, 0.0), ("end_joint", segment.end_joint, 1.0)] {
+            let Ok((_, joint_data)) = joints.get(joint) else {
+                errors.push(GraphError::Missing {
+                    entity: name(entity),
+                    field,
+                    missing: joint,
+                });
+                continue;
+            };
+            let connected = joint_data
+                .connections
+                .iter()
+                .flat_map(|c| &c.segments)
+                .any(|s| s.id == entity && s.t == t);
+            if !connected {
+                errors.push(GraphError::NotConnected {
+                    segment: name(entity),
+                    joint: name(joint),
+                    t,
+                });
+            }
+        }
+    }
+
+    for (entity, joint) in joints {
+        let mut location = None;
+        for (index, connection) in joint.connections.iter().enumerate() {
+            if connection.segments.is_empty() {
+                errors.push(GraphError::EmptyConnection { joint: name(entity), index });
+                continue;
+            }
+
+            // direction and curvature of the previous segment in this connection
+            let mut previous: Option<(Entity, (glam::Vec2, f32))> = None;
+            for s in &connection.segments {
+                let Ok((_, segment)) = segments.get(s.id) else {
+                    errors.push(GraphError::Missing {
+                        entity: name(entity),
+                        field: "connections",
+                        missing: s.id,
+                    });
+                    continue;
+                };
+                let own_end = match s.t {
+                    0.0 => segment.start_joint == entity,
+                    1.0 => segment.end_joint == entity,
+                    _ => false,
+                };
+                if !own_end {
+                    errors.push(GraphError::WrongEnd {
+                        joint: name(entity),
+                        segment: name(s.id),
+                        t: s.t,
+                    });
+                    continue;
+                }
+
+                let position = segment.curve.position(s.t);
+                let distance = position.distance(*location.get_or_insert(position));
+                if distance > POSITION_TOLERANCE {
+                    errors.push(GraphError::Misplaced {
+                        joint: name(entity),
+                        segment: name(s.id),
+                        t: s.t,
+                        distance,
+                    });
+                }
+
+                let (dir, curvature) = leaving(&segment.curve, s.t);
+                if let Some((prev, (prev_dir, prev_curvature))) = previous {
+                    let degrees = prev_dir.dot(dir).clamp(-1.0, 1.0).acos().to_degrees();
+                    if degrees > ANGLE_TOLERANCE {
+                        errors.push(GraphError::Tangents {
+                            joint: name(entity),
+                            a: name(prev),
+                            b: name(s.id),
+                            degrees,
+                        });
+                    }
+                    if prev_curvature > curvature + 1e-6 {
+                        errors.push(GraphError::CurvatureOrder {
+                            joint: name(entity),
+                            a: name(prev),
+                            b: name(s.id),
+                        });
+                    }
+                }
+                previous = Some((s.id, (dir, curvature)));
+            }
+        }
+    }
+
+    errors
+}
+
+/// Logs the problems of the track graph when a segment or joint is added or changed.
+pub fn validate_tracks(
+    changed: Query<(), Or<(Changed<Segment>, Changed<Joint>)>>,
+    segments: Query<(Entity, &Segment)>,
+    joints: Query<(Entity, &Joint)>,
+    names: Query<NameOrEntity>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    for e in validate_graph(&segments, &joints, &names) {
+        error!("track graph: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::builder::{Graph, GraphBuilder};
+    use crate::curve::{Joint, JointConnection, Segment, SegmentConnection};
+    use crate::validate::{validate_graph, GraphError};
+    use alloc::format;
+    use alloc::vec::Vec;
+    use bevy_ecs::name::NameOrEntity;
+    use bevy_ecs::prelude::*;
+    use bevy_ecs::system::RunSystemOnce;
+    use core::f32::consts::{FRAC_PI_2, PI};
+    use curve::arc::ArcSegment;
+    use curve::line::LineSegment;
+    use curve::traits::{CurveSegment, CurveType};
+    use glam::Vec2;
+
+    const TOP_LEFT: Vec2 = Vec2::new(100.0, 50.0);
+    const SCALE: f32 = 50.0;
+
+    /// The layout of `test_scenes::test_3_way_curve`: top, left, right, left-top, right-top and left-right.
+    fn three_way() -> Vec<CurveType> {
+        let at = |x: f32, y: f32| TOP_LEFT + Vec2::new(x, y) * SCALE;
+        alloc::vec![
+            LineSegment { start: at(2.0, 0.0), end: at(2.0, 1.0) }.into(),
+            LineSegment { start: at(0.0, 2.0), end: at(1.0, 2.0) }.into(),
+            LineSegment { start: at(4.0, 2.0), end: at(3.0, 2.0) }.into(),
+            ArcSegment { center: at(1.0, 1.0), start: -FRAC_PI_2, end: 0.0, radius: SCALE }.into(),
+            ArcSegment { center: at(3.0, 1.0), start: -FRAC_PI_2, end: -PI, radius: SCALE }.into(),
+            LineSegment { start: at(1.0, 2.0), end: at(3.0, 2.0) }.into(),
+        ]
+    }
+
+    /// Spawns the joints [`GraphBuilder`] finds for `curves`, named `Segment i` and `Joint i` by index.
+    fn spawn(world: &mut World, curves: Vec<CurveType>) -> (Vec<Entity>, Vec<Entity>) {
+        let builder = curves.iter().cloned().fold(GraphBuilder::new(), |graph, curve| graph.push(curve, 3));
+        let Graph { ends, joints } = builder.graph();
+
+        let segments: Vec<Entity> = (0..curves.len())
+            .map(|i| world.spawn(Name::new(format!("Segment {i}"))).id())
+            .collect();
+        let joint_entities: Vec<Entity> = (0..joints.len())
+            .map(|i| world.spawn(Name::new(format!("Joint {i}"))).id())
+            .collect();
+
+        for (connections, &entity) in joints.into_iter().zip(&joint_entities) {
+            let connections = connections
+                .into_iter()
+                .map(|group| JointConnection {
+                    segments: group
+                        .into_iter()
+                        .map(|(curve, t)| SegmentConnection { id: segments[curve], t })
+                        .collect(),
+                })
+                .collect();
+            world.entity_mut(entity).insert(Joint::new(connections));
+        }
+        for ((curve, (start, end)), &entity) in curves.into_iter().zip(ends).zip(&segments) {
+            world.entity_mut(entity).insert(Segment {
+                curve,
+                start_joint: joint_entities[start],
+                end_joint: joint_entities[end],
+            });
+        }
+
+        (segments, joint_entities)
+    }
+
+    fn validate(world: &mut World) -> Vec<GraphError> {
+        world
+            .run_system_once(
+                |segments: Query<(Entity, &Segment)>, joints: Query<(Entity, &Joint)>, names: Query<NameOrEntity>| {
+                    validate_graph(&segments, &joints, &names)
+                },
+            )
+            .unwrap()
+    }
+
+    fn connection(world: &mut World, joint: Entity, index: usize) -> Mut<'_, JointConnection> {
+        world
+            .get_mut::<Joint>(joint)
+            .unwrap()
+            .map_unchanged(|joint| &mut joint.connections[index])
+    }
+
+    #[test]
+    fn test_three_way_is_valid() {
+        let mut world = World::new();
+        spawn(&mut world, three_way());
+        let errors = validate(&mut world);
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn test_missing_joint() {
+        let mut world = World::new();
+        let (_, joints) = spawn(&mut world, three_way());
+        world.despawn(joints[0]);
+
+        assert_eq!(
+            validate(&mut world),
+            [GraphError::Missing {
+                entity: "Segment 0".into(),
+                field: "start_joint",
+                missing: joints[0],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_wrong_t() {
+        let mut world = World::new();
+        let (_, joints) = spawn(&mut world, three_way());
+        connection(&mut world, joints[0], 0).segments[0].t = 0.5;
+
+        assert_eq!(
+            validate(&mut world),
+            [
+                GraphError::NotConnected {
+                    segment: "Segment 0".into(),
+                    joint: "Joint 0".into(),
+                    t: 0.0,
+                },
+                GraphError::WrongEnd {
+                    joint: "Joint 0".into(),
+                    segment: "Segment 0".into(),
+                    t: 0.5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_misplaced_end() {
+        let mut world = World::new();
+        let (segments, _) = spawn(&mut world, three_way());
+        // the top line ends in the middle joint, which the others are measured from
+        let mut top = world.get_mut::<Segment>(segments[0]).unwrap();
+        let CurveType::Line(line) = &mut top.curve else { unreachable!() };
+        line.end.y += 5.0;
+
+        let errors = validate(&mut world);
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        for (error, name) in errors.iter().zip(["Segment 3", "Segment 4"]) {
+            let GraphError::Misplaced { joint, segment, t, distance } = error else {
+                panic!("expected a misplaced end, found {error:?}");
+            };
+            assert_eq!((joint.as_str(), segment.as_str(), *t), ("Joint 1", name, 1.0));
+            assert!((distance - 5.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_diverging_tangents() {
+        let mut world = World::new();
+        let (segments, _) = spawn(&mut world, three_way());
+        // a straight right-top leaves both its joints at 45 degrees to the track it shares them with
+        let mut right_top = world.get_mut::<Segment>(segments[4]).unwrap();
+        right_top.curve = LineSegment {
+            start: right_top.curve.position(0.0),
+            end: right_top.curve.position(1.0),
+        }
+        .into();
+
+        let errors = validate(&mut world);
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        for error in &errors {
+            let GraphError::Tangents { joint, a, b, degrees } = error else {
+                panic!("expected diverging tangents, found {error:?}");
+            };
+            let expected = match joint.as_str() {
+                "Joint 1" => ("Segment 3", "Segment 4"),
+                _ => ("Segment 4", "Segment 5"),
+            };
+            assert_eq!((a.as_str(), b.as_str()), expected, "{error}");
+            assert!((degrees - 45.0).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn test_wrong_curvature_order() {
+        let mut world = World::new();
+        let (_, joints) = spawn(&mut world, three_way());
+        // the left-top arc turns right and the right-top arc turns left when leaving downwards
+        connection(&mut world, joints[1], 1).segments.swap(0, 1);
+
+        assert_eq!(
+            validate(&mut world),
+            [GraphError::CurvatureOrder {
+                joint: "Joint 1".into(),
+                a: "Segment 4".into(),
+                b: "Segment 3".into(),
+            }]
+        );
+    }
+}